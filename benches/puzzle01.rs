@@ -3,16 +3,27 @@ use std::{
     io::{self, BufRead},
 };
 
-use aoc2020::{check_sum_equals, compute_product, self_cross_product};
+use aoc2020::generators::puzzle01::{generate, ExpensesParams};
+#[cfg(feature = "parallel")]
+use aoc2020::ksum::par_k_sum;
+use aoc2020::{
+    ksum::k_sum,
+    seq::{check_sum_equals, compute_product, k_combinations},
+};
 
 use criterion::Criterion;
 use criterion::{criterion_group, criterion_main};
+use itertools::Itertools;
 
+/// Brute force over the full cross product of the input with itself, pairs of the same entry
+/// and permutations of the same entries included.
 fn solve_part(input: &[i32], target: i32, dims: usize) -> Option<i64> {
-    let result: Vec<i64> = self_cross_product(input, dims)
-        .filter(|xs| check_sum_equals(&xs, target))
+    let result: Vec<i64> = (0..dims)
+        .map(|_| input.iter().cloned())
+        .multi_cartesian_product()
+        .filter(|xs| check_sum_equals(&xs, target).unwrap())
         .take(1)
-        .map(|xs| compute_product(&xs))
+        .map(|xs| compute_product(&xs).unwrap())
         .collect();
 
     match result.get(0) {
@@ -21,6 +32,12 @@ fn solve_part(input: &[i32], target: i32, dims: usize) -> Option<i64> {
     }
 }
 
+fn solve_part_combinations(input: &[i32], target: i32, dims: usize) -> Option<i64> {
+    k_combinations(input, dims)
+        .find(|xs| check_sum_equals(xs, target).unwrap())
+        .map(|xs| compute_product(&xs).unwrap())
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let file = File::open("inputs/puzzle01.input").unwrap();
     let lines = io::BufReader::new(file).lines();
@@ -40,6 +57,62 @@ fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("find_index_where (dimension: 3)", |b| {
         b.iter(|| solve_part(&input, 2020, 3))
     });
+
+    c.bench_function("k_combinations (dimension: 2)", |b| {
+        b.iter(|| solve_part_combinations(&input, 2020, 2))
+    });
+
+    c.bench_function("k_combinations (dimension: 3)", |b| {
+        b.iter(|| solve_part_combinations(&input, 2020, 3))
+    });
+
+    c.bench_function("k_sum (k: 2)", |b| b.iter(|| k_sum(&input, 2020, 2)));
+
+    c.bench_function("k_sum (k: 3)", |b| b.iter(|| k_sum(&input, 2020, 3)));
+
+    c.bench_function("k_sum (k: 4)", |b| b.iter(|| k_sum(&input, 2020, 4)));
+
+    let generated = generate(&ExpensesParams {
+        entries: 1_000_000,
+        k: 2,
+        target: 1_000_000,
+        seed: 1,
+    });
+    c.bench_function("k_sum (k: 2, generated 1M entries)", |b| {
+        b.iter(|| k_sum(&generated.entries, 1_000_000, 2))
+    });
+    #[cfg(feature = "parallel")]
+    c.bench_function("par_k_sum (k: 2, generated 1M entries)", |b| {
+        b.iter(|| par_k_sum(&generated.entries, 1_000_000, 2))
+    });
+
+    let generated = generate(&ExpensesParams {
+        entries: 5_000,
+        k: 3,
+        target: 1_000_000,
+        seed: 1,
+    });
+    c.bench_function("k_sum (k: 3, generated 5k entries)", |b| {
+        b.iter(|| k_sum(&generated.entries, 1_000_000, 3))
+    });
+    #[cfg(feature = "parallel")]
+    c.bench_function("par_k_sum (k: 3, generated 5k entries)", |b| {
+        b.iter(|| par_k_sum(&generated.entries, 1_000_000, 3))
+    });
+
+    let generated = generate(&ExpensesParams {
+        entries: 200,
+        k: 4,
+        target: 1_000_000,
+        seed: 1,
+    });
+    c.bench_function("k_sum (k: 4, generated 200 entries)", |b| {
+        b.iter(|| k_sum(&generated.entries, 1_000_000, 4))
+    });
+    #[cfg(feature = "parallel")]
+    c.bench_function("par_k_sum (k: 4, generated 200 entries)", |b| {
+        b.iter(|| par_k_sum(&generated.entries, 1_000_000, 4))
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
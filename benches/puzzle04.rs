@@ -0,0 +1,86 @@
+use aoc2020::generators::puzzle04::{generate, PassportParams};
+use aoc2020::passport::ColorMode;
+use aoc2020::puzzles::puzzle04::Passport;
+use aoc2020::schema::{parse_record, Schema};
+
+use criterion::Criterion;
+use criterion::{criterion_group, criterion_main};
+
+const PASSPORT: &str = include_str!("../schemas/passport.json");
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let generated = generate(&PassportParams {
+        passports: 100_000,
+        missing: 10,
+        invalid: 30,
+        shorthand: 5,
+        seed: 4,
+    });
+    let records = &generated.records;
+
+    // Compiling the patterns for every passport is too slow for the whole batch, so the
+    // comparison with a shared schema runs on the first 1k passports.
+    let sample = &records[..1_000];
+    let mut group = c.benchmark_group("generated (1k passports)");
+    group.bench_function("schema per passport", |b| {
+        b.iter(|| {
+            sample
+                .iter()
+                .filter(|raw| {
+                    let schema = Schema::from_json(PASSPORT).unwrap();
+                    schema.is_valid(&parse_record(raw).fields)
+                })
+                .count()
+        })
+    });
+
+    group.bench_function("shared schema", |b| {
+        let schema = Schema::passport();
+        b.iter(|| {
+            sample
+                .iter()
+                .filter(|raw| schema.is_valid(&parse_record(raw).fields))
+                .count()
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("generated (100k passports)");
+    group.sample_size(10);
+
+    group.bench_function("shared schema", |b| {
+        let schema = Schema::passport();
+        b.iter(|| {
+            records
+                .iter()
+                .filter(|raw| schema.is_valid(&parse_record(raw).fields))
+                .count()
+        })
+    });
+
+    group.bench_function("typed passports", |b| {
+        let schema = Schema::passport();
+        b.iter(|| {
+            records
+                .iter()
+                .filter(|raw| Passport::read(raw).validate_complete(schema).is_valid())
+                .count()
+        })
+    });
+
+    group.bench_function("typed passports (strict)", |b| {
+        let schema = Schema::passport_strict();
+        b.iter(|| {
+            records
+                .iter()
+                .map(|raw| Passport::read_with(raw, ColorMode::Strict))
+                .filter(|passport| passport.validate_complete(schema).is_valid())
+                .count()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
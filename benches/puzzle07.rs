@@ -0,0 +1,78 @@
+use std::{
+    fs,
+    io::{BufRead, Cursor},
+};
+
+use aoc2020::generators::puzzle07::{generate, RuleSetParams};
+use aoc2020::puzzles::puzzle07::{parse_rule, RuleSet};
+
+use criterion::Criterion;
+use criterion::{criterion_group, criterion_main};
+use regex::Regex;
+
+fn parse_rule_regex(
+    parent_re: &Regex,
+    content_re: &Regex,
+    line: &str,
+) -> Option<(String, Vec<(String, usize)>)> {
+    let cap = parent_re.captures(line)?;
+    let contents = content_re
+        .captures_iter(line)
+        .map(|bag| (bag[2].to_string(), bag[1].parse::<usize>().unwrap()))
+        .collect();
+
+    Some((cap[1].to_string(), contents))
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let input = fs::read_to_string("inputs/puzzle07.input").unwrap();
+    let lines: Vec<&str> = input.lines().collect();
+
+    c.bench_function("parse_rule (regex)", |b| {
+        let parent_re = Regex::new(r"(.+)\sbags\scontain\s(.*)\.").unwrap();
+        let content_re = Regex::new(r"(\d+)\s(.+?)\sbags?").unwrap();
+
+        b.iter(|| {
+            lines
+                .iter()
+                .filter_map(|line| parse_rule_regex(&parent_re, &content_re, line))
+                .count()
+        })
+    });
+
+    c.bench_function("parse_rule (grammar)", |b| {
+        b.iter(|| {
+            lines
+                .iter()
+                .filter_map(|line| parse_rule(line).ok())
+                .count()
+        })
+    });
+}
+
+fn generated_benchmark(c: &mut Criterion) {
+    let generated = generate(&RuleSetParams {
+        colors: 200_000,
+        fan_out: 4,
+        depth: 12,
+        quantity: 1..=3,
+        seed: 2020,
+    })
+    .unwrap();
+    let mut input = Vec::new();
+    generated.write(&mut input).unwrap();
+    let target = generated.color(1_000);
+
+    let mut group = c.benchmark_group("generated (200k rules)");
+    group.sample_size(10);
+    group.bench_function("RuleSet", |b| {
+        b.iter(|| {
+            let mut rules = RuleSet::from_lines(Cursor::new(&input).lines()).unwrap();
+            (rules.containers(target), rules.contents(target))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark, generated_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+use aoc2020::generators::puzzle09::{generate, XmasParams};
+use aoc2020::puzzles::puzzle09::{find_contiguous_sum, invalid_numbers};
+#[cfg(feature = "parallel")]
+use aoc2020::puzzles::puzzle09::{par_find_contiguous_sum, par_invalid_numbers};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use criterion::Criterion;
+use criterion::{criterion_group, criterion_main};
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let generated = generate(&XmasParams {
+        numbers: 1_000_000,
+        preamble: 25,
+        magnitude: 1_000_000_000,
+        seed: 1,
+    });
+    let answer = &generated.answer;
+
+    let mut group = c.benchmark_group("generated (1M numbers)");
+    group.sample_size(10);
+
+    group.bench_function("invalid_numbers", |b| {
+        b.iter(|| invalid_numbers(generated.numbers.iter().copied(), 25).count())
+    });
+
+    #[cfg(feature = "parallel")]
+    group.bench_function("par_invalid_numbers", |b| {
+        b.iter(|| par_invalid_numbers(&generated.numbers, 25).count())
+    });
+
+    group.bench_function("find_contiguous_sum", |b| {
+        b.iter(|| find_contiguous_sum(&generated.numbers, answer.invalid_number, 2))
+    });
+
+    #[cfg(feature = "parallel")]
+    group.bench_function("par_find_contiguous_sum", |b| {
+        b.iter(|| par_find_contiguous_sum(&generated.numbers, answer.invalid_number, 2))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -1,16 +1,505 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read},
+    process,
+};
+
+#[cfg(not(feature = "parallel"))]
+use aoc2020::ksum::k_sum;
+#[cfg(feature = "parallel")]
+use aoc2020::ksum::par_k_sum as k_sum;
+use aoc2020::{
+    batch::read_records,
+    ksum::{k_sum_all, k_sum_count},
+    passport::ColorMode,
+    puzzles::{
+        puzzle02::{AuditEntry, PolicyRegistry},
+        puzzle04::{csv_columns, csv_header, Passport},
+        puzzle09::validate_stream,
+    },
+    schema::{RecordError, Schema, Summary, Validation},
+    seq::compute_product,
+};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use itertools::process_results;
+use num_bigint::BigInt;
+use serde::Serialize;
+
 fn main() {
-    // let matches = App::new("AOC 2020")
-    //     .version("0.0.1")
-    //     .author("Diego Fernández <bigomby@gmail.com>")
-    //     .about("Advent of Code 2020")
-    //     .arg(
-    //         Arg::with_name("puzzle")
-    //             .short("p")
-    //             .long("puzzle")
-    //             .help("Select the puzzle to run")
-    //             .takes_value(true),
-    //     )
-    //     .get_matches();
-
-    // let puzzle = matches.value_of("puzzle").unwrap();
+    let registry = PolicyRegistry::default();
+    let policies: Vec<&str> = registry.names().collect();
+
+    let matches = App::new("AOC 2020")
+        .version("0.0.1")
+        .author("Diego Fernández <bigomby@gmail.com>")
+        .about("Advent of Code 2020")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("expenses")
+                .about("Finds expense report entries adding up to a target (puzzle 01)")
+                .arg(
+                    Arg::with_name("target")
+                        .short("t")
+                        .long("target")
+                        .help("Sum to look for")
+                        .takes_value(true)
+                        .default_value("2020"),
+                )
+                .arg(
+                    Arg::with_name("k")
+                        .short("k")
+                        .long("k")
+                        .help("Number of entries to combine")
+                        .takes_value(true)
+                        .default_value("2"),
+                )
+                .arg(
+                    Arg::with_name("all")
+                        .long("all")
+                        .help("Lists every combination adding up to the target"),
+                )
+                .arg(
+                    Arg::with_name("count")
+                        .long("count")
+                        .help("Only prints how many combinations add up to the target")
+                        .conflicts_with("all"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .help("Input file, use - for stdin")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("passports")
+                .about("Counts records complying with a validation schema (puzzle 04)")
+                .arg(
+                    Arg::with_name("schema")
+                        .short("s")
+                        .long("schema")
+                        .help("JSON schema file, defaults to the passport rules")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("strict")
+                        .long("strict")
+                        .help("Only takes hcl as #rrggbb with lowercase digits, as the puzzle says")
+                        .conflicts_with("schema"),
+                )
+                .arg(
+                    Arg::with_name("report")
+                        .long("report")
+                        .help("Prints the missing and invalid fields of every record"),
+                )
+                .arg(
+                    Arg::with_name("normalize")
+                        .long("normalize")
+                        .help("Prints every record with its fields in canonical order")
+                        .conflicts_with_all(&["report", "summary"]),
+                )
+                .arg(
+                    Arg::with_name("valid-only")
+                        .long("valid-only")
+                        .help("Only prints the records complying with the schema")
+                        .requires("normalize"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .help("Report or normalized output format, defaults to text")
+                        .takes_value(true)
+                        .possible_values(&["text", "csv", "json"]),
+                )
+                .arg(
+                    Arg::with_name("summary")
+                        .long("summary")
+                        .help("Prints how many records fail on each field"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .help("Input file with blank line separated records, use - for stdin")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("xmas")
+                .about(
+                    "Prints XMAS numbers that aren't the sum of two preamble numbers (puzzle 09)",
+                )
+                .arg(
+                    Arg::with_name("preamble")
+                        .short("p")
+                        .long("preamble")
+                        .help("Number of previous numbers to check against")
+                        .takes_value(true)
+                        .default_value("25"),
+                )
+                .arg(
+                    Arg::with_name("report")
+                        .long("report")
+                        .help("Prints the window each invalid number was checked against"),
+                )
+                .arg(
+                    Arg::with_name("explain")
+                        .long("explain")
+                        .help("Also reports valid numbers with the pair adding up to them")
+                        .requires("report"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .help("Report format, defaults to text")
+                        .takes_value(true)
+                        .possible_values(&["text", "json"])
+                        .requires("report"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .help("Input file, use - for stdin")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("passwords")
+                .about("Counts passwords complying with their policy (puzzle 02)")
+                .arg(
+                    Arg::with_name("policy")
+                        .short("p")
+                        .long("policy")
+                        .help("Policy kind the entries are checked with")
+                        .takes_value(true)
+                        .possible_values(&policies)
+                        .default_value("count"),
+                )
+                .arg(
+                    Arg::with_name("rule")
+                        .long("rule")
+                        .help("Checks every line as a bare password against this policy")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("report")
+                        .long("report")
+                        .help("Prints the verdict on every entry and why it failed"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .help("Report format, defaults to table")
+                        .takes_value(true)
+                        .possible_values(&["table", "json"])
+                        .requires("report"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .help("Input file with <policy>: <password> lines, use - for stdin")
+                        .required(true),
+                ),
+        )
+        .get_matches();
+
+    let result = match matches.subcommand() {
+        ("expenses", Some(m)) => expenses(m),
+        ("passports", Some(m)) => passports(m),
+        ("passwords", Some(m)) => passwords(m, &registry),
+        ("xmas", Some(m)) => xmas(m),
+        _ => unreachable!(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn open_input(path: &str) -> Result<Box<dyn BufRead>, Box<dyn Error>> {
+    if path == "-" {
+        Ok(Box::new(BufReader::new(io::stdin())))
+    } else {
+        Ok(Box::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+fn read_numbers(path: &str) -> Result<Vec<i32>, Box<dyn Error>> {
+    let mut numbers = Vec::new();
+
+    for line in open_input(path)?.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            numbers.push(line.trim().parse()?);
+        }
+    }
+
+    Ok(numbers)
+}
+
+fn expenses(m: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let target: i32 = m.value_of("target").unwrap().parse()?;
+    let k: usize = m.value_of("k").unwrap().parse()?;
+    let input = read_numbers(m.value_of("input").unwrap())?;
+
+    if m.is_present("count") {
+        println!("{}", k_sum_count(&input, target, k));
+        return Ok(());
+    }
+
+    if m.is_present("all") {
+        for combination in k_sum_all(&input, target, k) {
+            let indices: Vec<String> = combination.indices.iter().map(|i| i.to_string()).collect();
+            let values: Vec<String> = combination
+                .multiplicities
+                .iter()
+                .map(|(x, n)| format!("{}x{}", x, n))
+                .collect();
+
+            println!("{}\t{}", indices.join(","), values.join(" "));
+        }
+        return Ok(());
+    }
+
+    let entries = k_sum(&input, target, k).ok_or("Solution not found")?;
+    for (i, x) in &entries {
+        println!("{}\t{}", i, x);
+    }
+
+    let values: Vec<i32> = entries.into_iter().map(|(_, x)| x).collect();
+    println!("product: {}", compute_product::<i32, BigInt>(&values)?);
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct RecordReport {
+    record: usize,
+    line: usize,
+    valid: bool,
+    #[serde(flatten)]
+    validation: Validation,
+    errors: Vec<RecordError>,
+}
+
+fn passports(m: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let mode = if m.is_present("strict") {
+        ColorMode::Strict
+    } else {
+        ColorMode::Lenient
+    };
+    let schema = match (m.value_of("schema"), mode) {
+        (Some(path), _) => Schema::from_json(&fs::read_to_string(path)?)?,
+        (None, ColorMode::Strict) => Schema::passport_strict().clone(),
+        (None, ColorMode::Lenient) => Schema::passport().clone(),
+    };
+
+    let input = m.value_of("input").unwrap();
+    if m.is_present("normalize") {
+        return normalize(m, &schema, mode, input);
+    }
+
+    match m.value_of("format") {
+        Some("csv") => return Err("csv format is only available with --normalize".into()),
+        Some(_) if !m.is_present("report") => {
+            return Err("--format needs --report or --normalize".into())
+        }
+        _ => (),
+    }
+
+    // Records are checked as they are read, only the JSON report needs all of them at once.
+    let json = m.is_present("report") && m.value_of("format") == Some("json");
+    let mut reports = Vec::new();
+    let mut summary = Summary::new(&schema);
+    let mut valid = 0;
+    for (i, raw) in read_records(open_input(input)?).enumerate() {
+        let raw = raw?;
+        let passport = Passport::read_with(&raw.text(), mode);
+        let validation = passport.validate_complete(&schema);
+        let report = RecordReport {
+            record: i + 1,
+            line: raw.line,
+            valid: validation.is_valid(),
+            validation,
+            errors: passport.errors,
+        };
+
+        if report.valid {
+            valid += 1;
+        }
+        summary.add(&report.validation);
+
+        if json {
+            reports.push(report);
+        } else if m.is_present("report") {
+            let errors: Vec<String> = report.errors.iter().map(|e| e.to_string()).collect();
+            println!(
+                "{}\t{}\t{}\t{}",
+                report.record,
+                report.line,
+                report.validation,
+                errors.join("; ")
+            );
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    }
+
+    if m.is_present("summary") {
+        println!("{}", summary);
+    } else if !m.is_present("report") {
+        println!("{}", valid);
+    }
+
+    Ok(())
+}
+
+/// Prints the records in canonical form, reporting anything dropped while reading them on
+/// stderr. Text is printed as records are read and JSON once they all are. The CSV header
+/// lists the extra fields of every record, so the input is read twice, once for the columns
+/// and once for the rows. Standard input can't be read twice and is kept in memory instead.
+fn normalize(
+    m: &ArgMatches,
+    schema: &Schema,
+    mode: ColorMode,
+    input: &str,
+) -> Result<(), Box<dyn Error>> {
+    let valid_only = m.is_present("valid-only");
+    let keep = |passport: &Passport| !valid_only || passport.validate_complete(schema).is_valid();
+
+    match m.value_of("format") {
+        Some("json") => {
+            let passports = read_passports(open_input(input)?, mode, true);
+            let passports = process_results(passports, |passports| {
+                passports.filter(keep).collect::<Vec<_>>()
+            })?;
+            println!("{}", serde_json::to_string_pretty(&passports)?);
+        }
+        Some("csv") => {
+            let stdin = match input {
+                "-" => {
+                    let mut text = String::new();
+                    io::stdin().read_to_string(&mut text)?;
+                    Some(text)
+                }
+                _ => None,
+            };
+            let open = || -> Result<Box<dyn BufRead + '_>, Box<dyn Error>> {
+                match &stdin {
+                    Some(text) => Ok(Box::new(text.as_bytes())),
+                    None => open_input(input),
+                }
+            };
+
+            let passports = read_passports(open()?, mode, false);
+            let columns =
+                process_results(passports, |passports| csv_columns(passports.filter(keep)))?;
+
+            println!("{}", csv_header(&columns));
+            for passport in read_passports(open()?, mode, true) {
+                let passport = passport?;
+                if keep(&passport) {
+                    println!("{}", passport.to_csv(&columns));
+                }
+            }
+        }
+        _ => {
+            for passport in read_passports(open_input(input)?, mode, true) {
+                let passport = passport?;
+                if keep(&passport) {
+                    println!("{}", passport);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads passports one record at a time, printing anything dropped while reading them on
+/// stderr when `report` is set.
+fn read_passports<'a, R: BufRead + 'a>(
+    input: R,
+    mode: ColorMode,
+    report: bool,
+) -> impl Iterator<Item = io::Result<Passport>> + 'a {
+    read_records(input).map(move |raw| {
+        let raw = raw?;
+        let passport = Passport::read_with(&raw.text(), mode);
+        if report {
+            for error in &passport.errors {
+                eprintln!("line {}: {}", raw.line, error);
+            }
+        }
+
+        Ok(passport)
+    })
+}
+
+fn passwords(m: &ArgMatches, registry: &PolicyRegistry) -> Result<(), Box<dyn Error>> {
+    let policy = m.value_of("policy").unwrap();
+    let rule = match m.value_of("rule") {
+        Some(spec) => Some(registry.parse(policy, spec)?),
+        None => None,
+    };
+
+    let mut entries = Vec::new();
+    for (i, line) in open_input(m.value_of("input").unwrap())?
+        .lines()
+        .enumerate()
+    {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let entry = match &rule {
+            Some(rule) => AuditEntry::new(i + 1, rule.as_ref(), line.trim()),
+            None => AuditEntry::parse(i + 1, &line, registry, policy),
+        };
+
+        if entry.policy.is_none() && !m.is_present("report") {
+            return Err(format!("line {}: {}", entry.line, entry.reason.unwrap()).into());
+        }
+        entries.push(entry);
+    }
+
+    if !m.is_present("report") {
+        println!("{}", entries.iter().filter(|entry| entry.valid).count());
+    } else if m.value_of("format") == Some("json") {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+    } else {
+        println!("line\tverdict\tpolicy\tpassword\treason");
+        for entry in &entries {
+            println!("{}", entry);
+        }
+    }
+
+    Ok(())
+}
+
+fn xmas(m: &ArgMatches) -> Result<(), Box<dyn Error>> {
+    let preamble: usize = m.value_of("preamble").unwrap().parse()?;
+    let input = open_input(m.value_of("input").unwrap())?;
+
+    let stream = if m.is_present("explain") {
+        validate_stream(input, preamble).explain()
+    } else {
+        validate_stream(input, preamble)
+    };
+
+    if !m.is_present("report") {
+        for entry in stream {
+            let entry = entry?;
+            println!("{}\t{}", entry.index, entry.value);
+        }
+    } else if m.value_of("format") == Some("json") {
+        let entries = stream.collect::<Result<Vec<_>, _>>()?;
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+    } else {
+        for entry in stream {
+            println!("{}", entry?);
+        }
+    }
+
+    Ok(())
 }
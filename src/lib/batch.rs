@@ -0,0 +1,193 @@
+use std::io::{self, BufRead, Lines};
+
+/// What ends a record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordSeparator {
+    /// One or more blank lines, lines with only whitespace count as blank.
+    BlankLine,
+    /// A line holding just this marker, surrounding whitespace aside.
+    Line(String),
+}
+
+/// How the lines of a record are split into fields. Fields never span lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldSeparator {
+    /// Runs of whitespace, as in `key:value key:value`.
+    Whitespace,
+    /// Every non-blank line is a field.
+    Line,
+    /// This character, fields are trimmed and empty ones skipped.
+    Char(char),
+}
+
+/// A record read from a batch, along with where it starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawRecord {
+    /// Line the record starts at, counting from 1. Lines follow on from it, so the line at
+    /// `lines[i]` is `line + i`.
+    pub line: usize,
+    /// Lines of the record, without their `\n` or `\r\n` ending.
+    pub lines: Vec<String>,
+    separator: FieldSeparator,
+}
+
+impl RawRecord {
+    pub fn fields(&self) -> Vec<&str> {
+        let lines = self.lines.iter().map(String::as_str);
+        match self.separator {
+            FieldSeparator::Whitespace => lines.flat_map(str::split_whitespace).collect(),
+            FieldSeparator::Line => lines.filter(|line| !line.trim().is_empty()).collect(),
+            FieldSeparator::Char(c) => lines
+                .flat_map(|line| line.split(c))
+                .map(str::trim)
+                .filter(|field| !field.is_empty())
+                .collect(),
+        }
+    }
+
+    /// The lines of the record joined with `\n`.
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+}
+
+/// Reads records one at a time from a batch, by default blank line separated records of
+/// whitespace separated fields.
+pub fn read_records<R: BufRead>(input: R) -> RecordReader<R> {
+    RecordReader {
+        lines: input.lines(),
+        record_separator: RecordSeparator::BlankLine,
+        field_separator: FieldSeparator::Whitespace,
+        line_number: 0,
+    }
+}
+
+pub struct RecordReader<R> {
+    lines: Lines<R>,
+    record_separator: RecordSeparator,
+    field_separator: FieldSeparator,
+    line_number: usize,
+}
+
+impl<R> RecordReader<R> {
+    pub fn records_separated_by(mut self, separator: RecordSeparator) -> Self {
+        self.record_separator = separator;
+        self
+    }
+
+    pub fn fields_separated_by(mut self, separator: FieldSeparator) -> Self {
+        self.field_separator = separator;
+        self
+    }
+
+    fn is_separator(&self, line: &str) -> bool {
+        match &self.record_separator {
+            RecordSeparator::BlankLine => line.trim().is_empty(),
+            RecordSeparator::Line(marker) => line.trim() == marker,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for RecordReader<R> {
+    type Item = io::Result<RawRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record: Option<RawRecord> = None;
+
+        while let Some(line) = self.lines.next() {
+            self.line_number += 1;
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e)),
+            };
+
+            if self.is_separator(&line) {
+                if record.is_some() {
+                    break;
+                }
+            } else if let Some(record) = &mut record {
+                record.lines.push(line);
+            } else if !line.trim().is_empty() {
+                record = Some(RawRecord {
+                    line: self.line_number,
+                    lines: vec![line],
+                    separator: self.field_separator,
+                });
+            }
+        }
+
+        // Blank lines only make it into a record between a marker line and the next one.
+        let mut record = record?;
+        while record.lines.last().is_some_and(|l| l.trim().is_empty()) {
+            record.lines.pop();
+        }
+
+        Some(Ok(record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(input: &str) -> Vec<(usize, Vec<String>)> {
+        read_records(input.as_bytes())
+            .map(|record| {
+                let record = record.unwrap();
+                let fields = record.fields().into_iter().map(String::from).collect();
+                (record.line, fields)
+            })
+            .collect()
+    }
+
+    fn fields(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|field| field.to_string()).collect()
+    }
+
+    #[test]
+    fn blank_line_records() {
+        let expected = vec![
+            (1, fields(&["a:1", "b:2", "c:3"])),
+            (5, fields(&["d:4"])),
+            (7, fields(&["e:5", "f:6"])),
+        ];
+
+        assert_eq!(read("a:1 b:2\nc:3\n\n\nd:4\n\ne:5\nf:6"), expected);
+        assert_eq!(read("a:1 b:2\nc:3\n\n\nd:4\n\ne:5\nf:6\n\n"), expected);
+        assert_eq!(
+            read("a:1 b:2  \r\nc:3\r\n\r\n \t\r\nd:4\r\n\r\ne:5\tf:6\r\n"),
+            expected
+        );
+        assert_eq!(read("\n\n a:1\n").first().map(|r| r.0), Some(3));
+        assert!(read("").is_empty());
+        assert!(read("\r\n \n").is_empty());
+    }
+
+    #[test]
+    fn custom_separators() {
+        let input = "---\nname: a, size: 1\n\nsize: 2\n  ---  \n\n---\nname: b\n---\n";
+        let records: Vec<RawRecord> = read_records(input.as_bytes())
+            .records_separated_by(RecordSeparator::Line("---".to_string()))
+            .fields_separated_by(FieldSeparator::Char(','))
+            .collect::<io::Result<_>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].line, 2);
+        assert_eq!(
+            records[0].lines,
+            fields(&["name: a, size: 1", "", "size: 2"])
+        );
+        assert_eq!(records[0].fields(), vec!["name: a", "size: 1", "size: 2"]);
+        assert_eq!(records[1].line, 8);
+        assert_eq!(records[1].text(), "name: b");
+
+        let records: Vec<RawRecord> = read_records("ab\r\nac\r\n\r\nb".as_bytes())
+            .fields_separated_by(FieldSeparator::Line)
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(records[0].fields(), vec!["ab", "ac"]);
+        assert_eq!(records[0].text(), "ab\nac");
+        assert_eq!(records[1].fields(), vec!["b"]);
+    }
+}
@@ -0,0 +1,164 @@
+use serde::Serialize;
+use std::io::{self, Write};
+
+use super::Rng;
+use crate::seq::compute_product;
+
+#[derive(Debug, Clone)]
+pub struct ExpensesParams {
+    pub entries: usize,
+    pub k: usize,
+    pub target: i32,
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExpensesAnswer {
+    pub target: i32,
+    pub k: usize,
+    pub indices: Vec<usize>,
+    pub values: Vec<i32>,
+    /// `None` if the product overflows `i64`.
+    pub product: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratedExpenses {
+    pub entries: Vec<i32>,
+    pub answer: ExpensesAnswer,
+}
+
+/// Expense report with exactly one combination of `k` entries adding up to `target`.
+///
+/// Working modulo a prime `m > k`, planted entries are all `≡ r` with `k·r ≡ target` and filler
+/// entries are `≡ r + 1`. A combination with `j` filler entries adds up to `target + j (mod m)`,
+/// so only the planted entries can reach the target.
+pub fn generate(params: &ExpensesParams) -> GeneratedExpenses {
+    let k = params.k;
+    let m = (k as i64 + 1..).find(|&x| is_prime(x)).unwrap();
+    let target = params.target as i64;
+    let r = (target.rem_euclid(m) * mod_inverse(k as i64, m)).rem_euclid(m);
+
+    assert!(k >= 1 && params.entries >= k, "Not enough entries for k");
+    assert!(target >= k as i64 * (m + r), "Target too small for k");
+
+    let mut rng = Rng::new(params.seed);
+    let congruent = |rng: &mut Rng, max: i64, residue: i64| {
+        let x = rng.range_i64(0, (max - residue) / m);
+        x * m + residue
+    };
+
+    let mut values = Vec::with_capacity(k);
+    let mut remaining = target;
+    for left in (1..k).rev() {
+        let value = congruent(&mut rng, remaining / (left as i64 + 1), r).max(r + m);
+        values.push(value as i32);
+        remaining -= value;
+    }
+    values.push(remaining as i32);
+
+    let filler_residue = (r + 1) % m;
+    let mut entries: Vec<i32> = (0..params.entries - k)
+        .map(|_| congruent(&mut rng, target, filler_residue) as i32)
+        .collect();
+
+    let mut indices = Vec::with_capacity(k);
+    for value in &values {
+        let index = rng.range(0, entries.len() as u64) as usize;
+        entries.insert(index, *value);
+        indices.iter_mut().for_each(|i| {
+            if *i >= index {
+                *i += 1
+            }
+        });
+        indices.push(index);
+    }
+
+    let mut planted: Vec<(usize, i32)> = indices.into_iter().zip(values).collect();
+    planted.sort_unstable();
+
+    let (indices, values): (Vec<usize>, Vec<i32>) = planted.into_iter().unzip();
+    let product = compute_product(&values).ok();
+
+    GeneratedExpenses {
+        entries,
+        answer: ExpensesAnswer {
+            target: params.target,
+            k,
+            indices,
+            values,
+            product,
+        },
+    }
+}
+
+fn is_prime(n: i64) -> bool {
+    n >= 2 && (2..).take_while(|d| d * d <= n).all(|d| n % d != 0)
+}
+
+fn mod_inverse(a: i64, m: i64) -> i64 {
+    (1..m).find(|x| (a * x) % m == 1).unwrap()
+}
+
+impl GeneratedExpenses {
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for entry in &self.entries {
+            writeln!(out, "{}", entry)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_answer<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, &self.answer)?;
+        writeln!(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ksum::{k_sum, k_sum_count};
+
+    #[test]
+    fn planted_solution() {
+        for k in 1..=4 {
+            let generated = generate(&ExpensesParams {
+                entries: 400,
+                k,
+                target: 2020,
+                seed: k as u64,
+            });
+            let answer = &generated.answer;
+
+            assert_eq!(generated.entries.len(), 400);
+            assert_eq!(answer.values.iter().sum::<i32>(), 2020);
+            for (i, x) in answer.indices.iter().zip(&answer.values) {
+                assert_eq!(generated.entries[*i], *x);
+            }
+
+            let found = k_sum(&generated.entries, 2020, k).unwrap();
+            assert_eq!(
+                found.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+                answer.indices
+            );
+            assert_eq!(k_sum_count(&generated.entries, 2020, k), 1);
+        }
+    }
+
+    #[test]
+    fn large_target() {
+        let generated = generate(&ExpensesParams {
+            entries: 5_000,
+            k: 2,
+            target: 1_000_000,
+            seed: 1,
+        });
+        let found = k_sum(&generated.entries, 1_000_000, 2).unwrap();
+
+        assert_eq!(
+            found.iter().map(|(_, x)| *x).collect::<Vec<_>>(),
+            generated.answer.values
+        );
+    }
+}
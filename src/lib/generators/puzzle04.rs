@@ -0,0 +1,179 @@
+use serde::Serialize;
+use std::io::{self, Write};
+
+use super::Rng;
+
+const REQUIRED: [&str; 7] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"];
+const EYE_COLORS: [&str; 7] = ["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
+
+#[derive(Debug, Clone)]
+pub struct PassportParams {
+    pub passports: usize,
+    /// Chance, in percent, of a passport missing a required field.
+    pub missing: u64,
+    /// Chance, in percent, of a passport having all its fields with one of them invalid.
+    pub invalid: u64,
+    /// Chance, in percent, of a valid hair color being written in the `#rgb` shorthand.
+    pub shorthand: u64,
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PassportAnswer {
+    /// Passports with every required field.
+    pub present: usize,
+    /// Passports complying with the passport rules.
+    pub valid: usize,
+    /// Valid passports that are also valid under the strict hair color rules.
+    pub strict_valid: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratedPassports {
+    pub records: Vec<String>,
+    pub answer: PassportAnswer,
+}
+
+/// Passport batch in the puzzle format: fields are shuffled, split over one to three lines and
+/// `cid` shows up in half of the passports. Passports are either missing one required field,
+/// have one invalid value, or are valid.
+pub fn generate(params: &PassportParams) -> GeneratedPassports {
+    let mut rng = Rng::new(params.seed);
+    let mut records = Vec::with_capacity(params.passports);
+    let mut answer = PassportAnswer {
+        present: 0,
+        valid: 0,
+        strict_valid: 0,
+    };
+
+    for _ in 0..params.passports {
+        let shorthand = rng.range(1, 100) <= params.shorthand;
+        let mut fields: Vec<(&str, String)> = REQUIRED
+            .iter()
+            .map(|&key| (key, valid_value(&mut rng, key, shorthand)))
+            .collect();
+
+        if rng.range(1, 100) <= params.missing {
+            fields.swap_remove(rng.range(0, fields.len() as u64 - 1) as usize);
+        } else if rng.range(1, 100) <= params.invalid {
+            let i = rng.range(0, fields.len() as u64 - 1) as usize;
+            fields[i].1 = invalid_value(&mut rng, fields[i].0);
+            answer.present += 1;
+        } else {
+            answer.present += 1;
+            answer.valid += 1;
+            if !shorthand {
+                answer.strict_valid += 1;
+            }
+        }
+
+        if rng.range(0, 1) == 1 {
+            fields.push(("cid", rng.range(1, 999).to_string()));
+        }
+        rng.shuffle(&mut fields);
+
+        let mut record = String::new();
+        for (i, (key, value)) in fields.iter().enumerate() {
+            if i > 0 {
+                record.push(if rng.range(0, 3) == 0 { '\n' } else { ' ' });
+            }
+            record.push_str(&format!("{}:{}", key, value));
+        }
+        records.push(record);
+    }
+
+    GeneratedPassports { records, answer }
+}
+
+fn valid_value(rng: &mut Rng, key: &str, shorthand: bool) -> String {
+    match key {
+        "byr" => rng.range(1920, 2002).to_string(),
+        "iyr" => rng.range(2010, 2020).to_string(),
+        "eyr" => rng.range(2020, 2030).to_string(),
+        "hgt" if rng.range(0, 1) == 0 => format!("{}cm", rng.range(150, 193)),
+        "hgt" => format!("{}in", rng.range(59, 76)),
+        "hcl" if shorthand => format!("#{:03x}", rng.range(0, 0xfff)),
+        "hcl" => format!("#{:06x}", rng.range(0, 0xff_ffff)),
+        "ecl" => EYE_COLORS[rng.range(0, 6) as usize].to_string(),
+        "pid" => format!("{:09}", rng.range(0, 999_999_999)),
+        _ => unreachable!(),
+    }
+}
+
+fn invalid_value(rng: &mut Rng, key: &str) -> String {
+    match key {
+        "byr" => rng.range(2021, 2100).to_string(),
+        "iyr" => rng.range(1900, 2009).to_string(),
+        "eyr" => rng.range(2031, 2100).to_string(),
+        "hgt" if rng.range(0, 1) == 0 => format!("{}cm", rng.range(194, 250)),
+        "hgt" => rng.range(59, 76).to_string(),
+        "hcl" => format!("{:06x}", rng.range(0, 0xff_ffff)),
+        "ecl" => "zzz".to_string(),
+        "pid" => format!("{:010}", rng.range(0, 9_999_999_999)),
+        _ => unreachable!(),
+    }
+}
+
+impl GeneratedPassports {
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for (i, record) in self.records.iter().enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "{}", record)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_answer<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, &self.answer)?;
+        writeln!(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{passport::ColorMode, puzzles::puzzle04::Passport, schema::Schema};
+
+    #[test]
+    fn matches_schema() {
+        for seed in 0..5 {
+            let generated = generate(&PassportParams {
+                passports: 2_000,
+                missing: 10,
+                invalid: 30,
+                shorthand: 20,
+                seed,
+            });
+            let answer = &generated.answer;
+            assert!(answer.strict_valid < answer.valid && answer.valid < answer.present);
+
+            let count = |mode, schema: &Schema| {
+                generated
+                    .records
+                    .iter()
+                    .map(|raw| Passport::read_with(raw, mode))
+                    .filter(|passport| passport.validate_complete(schema).is_valid())
+                    .count()
+            };
+            assert_eq!(count(ColorMode::Lenient, Schema::passport()), answer.valid);
+            assert_eq!(
+                count(ColorMode::Strict, Schema::passport_strict()),
+                answer.strict_valid
+            );
+            assert_eq!(
+                count(ColorMode::Lenient, Schema::passport_strict()),
+                answer.strict_valid
+            );
+
+            let present = generated
+                .records
+                .iter()
+                .filter(|raw| Passport::read(raw).validate_presence())
+                .count();
+            assert_eq!(present, answer.present);
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    error::Error,
+    io::{self, Write},
+    ops::RangeInclusive,
+};
+
+use super::Rng;
+
+const ADJECTIVES: &[&str] = &[
+    "bright", "clear", "dark", "dim", "dotted", "drab", "dull", "faded", "light", "mirrored",
+    "muted", "pale", "plaid", "posh", "shiny", "striped", "vibrant", "wavy",
+];
+
+const HUES: &[&str] = &[
+    "aqua",
+    "beige",
+    "black",
+    "blue",
+    "bronze",
+    "brown",
+    "coral",
+    "crimson",
+    "cyan",
+    "fuchsia",
+    "gold",
+    "gray",
+    "green",
+    "indigo",
+    "lavender",
+    "lime",
+    "magenta",
+    "maroon",
+    "olive",
+    "orange",
+    "plum",
+    "purple",
+    "red",
+    "salmon",
+    "silver",
+    "tan",
+    "teal",
+    "tomato",
+    "turquoise",
+    "violet",
+    "white",
+    "yellow",
+];
+
+#[derive(Debug, Clone)]
+pub struct RuleSetParams {
+    pub colors: usize,
+    pub fan_out: usize,
+    pub depth: usize,
+    pub quantity: RangeInclusive<usize>,
+    pub seed: u64,
+}
+
+/// Expected answers for a target color, `contents` is `None` if the total overflows `usize`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleSetAnswers {
+    pub containers: usize,
+    pub contents: Option<usize>,
+}
+
+/// Acyclic rule set: colors are split in `depth + 1` levels and bags only contain colors of
+/// deeper levels, so a color always has a higher index than the bags containing it.
+#[derive(Debug, Clone)]
+pub struct GeneratedRuleSet {
+    names: Vec<String>,
+    contents: Vec<Vec<(usize, usize)>>,
+    order: Vec<usize>,
+}
+
+/// Fails if `params.quantity` is empty or allows `0`, since `0 <color> bags` isn't a valid rule.
+pub fn generate(params: &RuleSetParams) -> Result<GeneratedRuleSet, Box<dyn Error>> {
+    let (lo, hi) = (*params.quantity.start(), *params.quantity.end());
+    if lo == 0 {
+        return Err("Bag quantities must start at 1".into());
+    }
+    if lo > hi {
+        return Err(format!("Empty bag quantity range {}..={}", lo, hi).into());
+    }
+
+    let mut rng = Rng::new(params.seed);
+    let levels = params.depth + 1;
+    let n = params.colors;
+    let level_start = |level: usize| (level * n).div_ceil(levels);
+
+    let names = (0..n).map(color_name).collect();
+    let contents = (0..n)
+        .map(|i| {
+            let level = i * levels / n;
+            if level + 1 >= levels {
+                return Vec::new();
+            }
+
+            let deeper = level_start(level + 1)..n;
+            let next_level = level_start(level + 1)..level_start(level + 2).min(n);
+            let fan_out = params.fan_out.min(deeper.len());
+
+            let mut children = HashSet::new();
+            let mut picked = Vec::new();
+            while picked.len() < fan_out {
+                let range = if picked.is_empty() && !next_level.is_empty() {
+                    &next_level
+                } else {
+                    &deeper
+                };
+                let child = rng.range(range.start as u64, range.end as u64 - 1) as usize;
+
+                if children.insert(child) {
+                    let amount = rng.range(lo as u64, hi as u64);
+                    picked.push((child, amount as usize));
+                }
+            }
+
+            picked
+        })
+        .collect();
+
+    let mut order: Vec<usize> = (0..n).collect();
+    rng.shuffle(&mut order);
+
+    Ok(GeneratedRuleSet {
+        names,
+        contents,
+        order,
+    })
+}
+
+fn color_name(i: usize) -> String {
+    let adjective = ADJECTIVES[i % ADJECTIVES.len()];
+    let mut rest = i / ADJECTIVES.len();
+    let mut hue = HUES[rest % HUES.len()].to_string();
+
+    rest /= HUES.len();
+    while rest > 0 {
+        hue.push((b'a' + (rest % 26) as u8) as char);
+        rest /= 26;
+    }
+
+    format!("{} {}", adjective, hue)
+}
+
+impl GeneratedRuleSet {
+    pub fn color(&self, index: usize) -> &str {
+        &self.names[index]
+    }
+
+    /// Rules in puzzle07 format, in shuffled order.
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.order.iter().map(move |&i| self.rule(i))
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for line in self.lines() {
+            writeln!(out, "{}", line)?;
+        }
+
+        Ok(())
+    }
+
+    fn rule(&self, i: usize) -> String {
+        let contents = match &self.contents[i][..] {
+            [] => "no other bags".to_string(),
+            xs => xs
+                .iter()
+                .map(|&(child, amount)| {
+                    let noun = if amount == 1 { "bag" } else { "bags" };
+                    format!("{} {} {}", amount, self.names[child], noun)
+                })
+                .collect::<Vec<String>>()
+                .join(", "),
+        };
+
+        format!("{} bags contain {}.", self.names[i], contents)
+    }
+
+    /// Reference answers for `target`, computed directly on the generated levels.
+    pub fn answers(&self, target: &str) -> Option<RuleSetAnswers> {
+        let target = self.names.iter().position(|name| name == target)?;
+
+        let mut parents = vec![Vec::new(); self.names.len()];
+        for (parent, xs) in self.contents.iter().enumerate() {
+            for &(child, _) in xs {
+                parents[child].push(parent);
+            }
+        }
+
+        let mut seen = vec![false; self.names.len()];
+        let mut queue = VecDeque::from(vec![target]);
+        while let Some(current) = queue.pop_front() {
+            for &parent in &parents[current] {
+                if !seen[parent] {
+                    seen[parent] = true;
+                    queue.push_back(parent);
+                }
+            }
+        }
+
+        let mut totals: Vec<Option<usize>> = vec![Some(0); self.names.len()];
+        for i in (target..self.names.len()).rev() {
+            totals[i] = self.contents[i]
+                .iter()
+                .try_fold(0usize, |sum, &(child, amount)| {
+                    let inner = totals[child]?.checked_add(1)?.checked_mul(amount)?;
+                    sum.checked_add(inner)
+                });
+        }
+
+        Some(RuleSetAnswers {
+            containers: seen.iter().filter(|&&s| s).count(),
+            contents: totals[target],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{graph::PathSumError, puzzles::puzzle07::RuleSet};
+
+    use std::io::{BufRead, Cursor};
+
+    fn params(seed: u64) -> RuleSetParams {
+        RuleSetParams {
+            colors: 2_000,
+            fan_out: 4,
+            depth: 8,
+            quantity: 1..=5,
+            seed,
+        }
+    }
+
+    #[test]
+    fn reproducible() {
+        let a: Vec<String> = generate(&params(7)).unwrap().lines().collect();
+        let b: Vec<String> = generate(&params(7)).unwrap().lines().collect();
+        let c: Vec<String> = generate(&params(8)).unwrap().lines().collect();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn matches_rule_set() {
+        for seed in 0..5 {
+            let generated = generate(&params(seed)).unwrap();
+            let mut output = Vec::new();
+            generated.write(&mut output).unwrap();
+
+            let mut rules = RuleSet::from_lines(Cursor::new(output).lines()).unwrap();
+            for index in [0, 300, 1_000, 1_999].iter() {
+                let target = generated.color(*index);
+                let answers = generated.answers(target).unwrap();
+
+                assert_eq!(rules.containers(target), answers.containers);
+                assert_eq!(rules.contents(target).ok(), answers.contents);
+            }
+        }
+    }
+
+    #[test]
+    fn overflowing_contents() {
+        let generated = generate(&RuleSetParams {
+            colors: 2_000,
+            fan_out: 6,
+            depth: 12,
+            quantity: 50..=100,
+            seed: 1,
+        })
+        .unwrap();
+        let mut output = Vec::new();
+        generated.write(&mut output).unwrap();
+
+        let mut rules = RuleSet::from_lines(Cursor::new(output).lines()).unwrap();
+        let mut overflows = 0;
+        for index in (0..2_000).step_by(25) {
+            let target = generated.color(index);
+            let contents = rules.contents(target);
+
+            if contents == Err(PathSumError::Overflow) {
+                overflows += 1;
+            }
+            assert_eq!(contents.ok(), generated.answers(target).unwrap().contents);
+        }
+        assert!(overflows > 0 && overflows < 80);
+    }
+
+    #[test]
+    fn invalid_quantities() {
+        let with_quantity = |quantity| RuleSetParams {
+            quantity,
+            ..params(1)
+        };
+
+        assert!(generate(&with_quantity(0..=3)).is_err());
+        assert!(generate(&with_quantity(RangeInclusive::new(5, 1))).is_err());
+        assert!(generate(&with_quantity(2..=2)).is_ok());
+    }
+}
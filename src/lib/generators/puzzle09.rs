@@ -0,0 +1,205 @@
+use serde::Serialize;
+use std::{
+    cmp::Ordering,
+    collections::{HashSet, VecDeque},
+    io::{self, Write},
+};
+
+use super::Rng;
+
+/// Kept in the window at all times so that the numbers it can produce never collapse to a
+/// multiple of some large value.
+const ANCHORS: [i64; 3] = [0, 1, -1];
+
+#[derive(Debug, Clone)]
+pub struct XmasParams {
+    pub numbers: usize,
+    pub preamble: usize,
+    /// Largest absolute value of the valid numbers.
+    pub magnitude: i64,
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct XmasAnswer {
+    pub preamble: usize,
+    pub invalid_index: usize,
+    pub invalid_number: i64,
+    /// Bounds `start..end` of the contiguous range adding up to the invalid number.
+    pub range: (usize, usize),
+    /// Smallest plus largest number of the range.
+    pub weakness: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratedXmas {
+    pub numbers: Vec<i64>,
+    pub answer: XmasAnswer,
+}
+
+/// XMAS stream where every number but one is the sum of two numbers of its window.
+///
+/// The stream starts with the anchors `0, 1, -1`, which are copied again before they leave the
+/// window. Other valid numbers add up a random pair whose sum is non-zero, stays within
+/// `magnitude`, isn't already in the window and preferably has the sign that is less common in
+/// it, so the window never drifts to a single sign. The invalid number is the sum of the first
+/// `e` numbers for the first `e` where that sum can't be made from the window and no shorter
+/// prefix adds up to it, and is placed right after them, so the range with the smallest start
+/// is `0..e`.
+pub fn generate(params: &XmasParams) -> GeneratedXmas {
+    let preamble = params.preamble;
+    assert!(preamble > ANCHORS.len(), "Preamble too short");
+    assert!(
+        params.numbers > preamble,
+        "Not enough numbers for the preamble"
+    );
+
+    let mut rng = Rng::new(params.seed);
+    let mut numbers = Vec::with_capacity(params.numbers);
+    let mut window: VecDeque<i64> = VecDeque::with_capacity(preamble);
+    let mut prefixes: HashSet<i128> = HashSet::new();
+    let mut prefix: i128 = 0;
+    let mut answer = None;
+
+    while numbers.len() < params.numbers {
+        let index = numbers.len();
+        let value = if index < ANCHORS.len() {
+            ANCHORS[index]
+        } else if index < preamble {
+            rng.range_i64(-params.magnitude, params.magnitude)
+        } else if let Some(anchor) = expiring_anchor(&window) {
+            anchor
+        } else if answer.is_none()
+            && index >= 2
+            && !prefixes.contains(&prefix)
+            && i64::MAX as i128 >= prefix.abs()
+            && find_pair(&window, prefix as i64).is_none()
+        {
+            let invalid_number = prefix as i64;
+            let range = &numbers[..index];
+            let weakness = range.iter().min().unwrap() + range.iter().max().unwrap();
+
+            answer = Some(XmasAnswer {
+                preamble,
+                invalid_index: index,
+                invalid_number,
+                range: (0, index),
+                weakness,
+            });
+
+            invalid_number
+        } else {
+            valid_number(&mut rng, &window, params.magnitude)
+        };
+
+        if answer.is_none() {
+            if index >= 2 {
+                prefixes.insert(prefix);
+            }
+            prefix += value as i128;
+        }
+
+        numbers.push(value);
+        window.push_back(value);
+        if window.len() > preamble {
+            window.pop_front();
+        }
+    }
+
+    GeneratedXmas {
+        numbers,
+        answer: answer.expect("Not enough numbers to plant an invalid one"),
+    }
+}
+
+/// Anchor about to leave the window, anchors are re-emitted as `1 + 0`, `1 + -1` and `-1 + 0`.
+fn expiring_anchor(window: &VecDeque<i64>) -> Option<i64> {
+    ANCHORS
+        .iter()
+        .filter_map(|&anchor| Some((window.iter().rposition(|&x| x == anchor)?, anchor)))
+        .filter(|&(position, _)| position < ANCHORS.len())
+        .min()
+        .map(|(_, anchor)| anchor)
+}
+
+fn valid_number(rng: &mut Rng, window: &VecDeque<i64>, magnitude: i64) -> i64 {
+    let negatives = window.iter().filter(|&&x| x < 0).count();
+    let want_negative = match (negatives * 2).cmp(&window.len()) {
+        Ordering::Less => true,
+        Ordering::Greater => false,
+        Ordering::Equal => rng.range(0, 1) == 0,
+    };
+    let fresh = |sum: i128| {
+        sum != 0 && sum.abs() <= magnitude as i128 && !window.iter().any(|&x| x as i128 == sum)
+    };
+    let pick = |rng: &mut Rng| rng.range(0, window.len() as u64 - 1) as usize;
+
+    for attempt in 0..128 {
+        let (i, j) = (pick(rng), pick(rng));
+        let sum = window[i] as i128 + window[j] as i128;
+
+        if i != j && fresh(sum) && (attempt >= 64 || (sum < 0) == want_negative) {
+            return sum as i64;
+        }
+    }
+
+    let sums: Vec<i128> = (0..window.len())
+        .flat_map(|i| (i + 1..window.len()).map(move |j| (i, j)))
+        .map(|(i, j)| window[i] as i128 + window[j] as i128)
+        .collect();
+
+    let sum = match sums.iter().find(|&&sum| fresh(sum)) {
+        Some(sum) => *sum,
+        None => *sums.iter().min_by_key(|sum| sum.abs()).unwrap(),
+    };
+
+    sum as i64
+}
+
+fn find_pair(window: &VecDeque<i64>, n: i64) -> Option<(i64, i64)> {
+    (0..window.len())
+        .flat_map(|i| (i + 1..window.len()).map(move |j| (i, j)))
+        .map(|(i, j)| (window[i], window[j]))
+        .find(|&(a, b)| a as i128 + b as i128 == n as i128)
+}
+
+impl GeneratedXmas {
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for n in &self.numbers {
+            writeln!(out, "{}", n)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_answer<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, &self.answer)?;
+        writeln!(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzles::puzzle09::{find_contiguous_sum, invalid_numbers};
+
+    #[test]
+    fn planted_answer() {
+        for seed in 0..5 {
+            let generated = generate(&XmasParams {
+                numbers: 2_000,
+                preamble: 25,
+                magnitude: 1_000_000_000,
+                seed,
+            });
+            let answer = &generated.answer;
+            let numbers = &generated.numbers;
+
+            let invalid: Vec<(usize, i64)> = invalid_numbers(numbers.iter().cloned(), 25).collect();
+            assert_eq!(invalid, vec![(answer.invalid_index, answer.invalid_number)]);
+
+            let range = find_contiguous_sum(numbers, answer.invalid_number, 2).unwrap();
+            assert_eq!((range.start, range.end), answer.range);
+        }
+    }
+}
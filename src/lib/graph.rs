@@ -0,0 +1,444 @@
+use num_traits::{CheckedAdd, CheckedMul, One, Zero};
+use std::{
+    borrow::Borrow,
+    collections::{HashMap, HashSet, VecDeque},
+    error::Error,
+    fmt,
+    hash::Hash,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Forward,
+    Reverse,
+}
+
+/// Returned when an operation requires an acyclic graph, holds a node on the cycle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CycleError(pub usize);
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cycle found at node {}", self.0)
+    }
+}
+
+impl Error for CycleError {}
+
+/// Returned by [`Graph::path_product_sum`] when the graph has a cycle, holding a node on it, or
+/// when the sum doesn't fit in the weight type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSumError {
+    Cycle(usize),
+    Overflow,
+}
+
+impl fmt::Display for PathSumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSumError::Cycle(id) => write!(f, "Cycle found at node {}", id),
+            PathSumError::Overflow => write!(f, "Arithmetic overflow"),
+        }
+    }
+}
+
+impl Error for PathSumError {}
+
+/// Directed graph with labeled nodes and weighted edges. Nodes are addressed by the `usize`
+/// id returned by [`Graph::add_node`].
+#[derive(Debug, Clone)]
+pub struct Graph<N, W> {
+    labels: Vec<N>,
+    index: HashMap<N, usize>,
+    forward: Vec<Vec<(usize, W)>>,
+    reverse: Vec<Vec<(usize, W)>>,
+}
+
+impl<N, W> Default for Graph<N, W> {
+    fn default() -> Self {
+        Self {
+            labels: Vec::new(),
+            index: HashMap::new(),
+            forward: Vec::new(),
+            reverse: Vec::new(),
+        }
+    }
+}
+
+impl<N: Hash + Eq + Clone, W: Copy> Graph<N, W> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    pub fn node<Q>(&self, label: &Q) -> Option<usize>
+    where
+        N: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.index.get(label).copied()
+    }
+
+    pub fn label(&self, id: usize) -> &N {
+        &self.labels[id]
+    }
+
+    /// Returns the id of `label`, inserting it if needed.
+    pub fn add_node(&mut self, label: N) -> usize {
+        if let Some(&id) = self.index.get(&label) {
+            return id;
+        }
+
+        let id = self.labels.len();
+        self.index.insert(label.clone(), id);
+        self.labels.push(label);
+        self.forward.push(Vec::new());
+        self.reverse.push(Vec::new());
+
+        id
+    }
+
+    pub fn add_edge(&mut self, from: usize, to: usize, weight: W) {
+        self.forward[from].push((to, weight));
+        self.reverse[to].push((from, weight));
+    }
+
+    /// Removes every edge leaving `from`, returning them.
+    pub fn remove_edges(&mut self, from: usize) -> Vec<(usize, W)> {
+        let edges = std::mem::take(&mut self.forward[from]);
+
+        for (to, _) in &edges {
+            self.reverse[*to].retain(|(parent, _)| *parent != from);
+        }
+
+        edges
+    }
+
+    pub fn edges(&self, id: usize, direction: Direction) -> &[(usize, W)] {
+        match direction {
+            Direction::Forward => &self.forward[id],
+            Direction::Reverse => &self.reverse[id],
+        }
+    }
+
+    pub fn weight(&self, from: usize, to: usize) -> Option<W> {
+        self.forward[from]
+            .iter()
+            .find(|(id, _)| *id == to)
+            .map(|(_, w)| *w)
+    }
+
+    /// Depth-first, pre-order traversal starting (and including) `start`.
+    pub fn dfs(&self, start: usize, direction: Direction) -> Dfs<'_, N, W> {
+        Dfs {
+            graph: self,
+            direction,
+            stack: vec![start],
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Breadth-first traversal starting (and including) `start`.
+    pub fn bfs(&self, start: usize, direction: Direction) -> Bfs<'_, N, W> {
+        Bfs {
+            graph: self,
+            direction,
+            queue: VecDeque::from(vec![start]),
+            seen: vec![start].into_iter().collect(),
+        }
+    }
+
+    /// Nodes reachable from `start` other than `start` itself.
+    pub fn reachable(&self, start: usize, direction: Direction) -> HashSet<usize> {
+        self.bfs(start, direction).skip(1).collect()
+    }
+
+    /// Kahn's algorithm, parents always come before their children.
+    pub fn topological_sort(&self) -> Result<Vec<usize>, CycleError> {
+        let mut in_degree: Vec<usize> = self.reverse.iter().map(Vec::len).collect();
+        let mut queue: VecDeque<usize> = (0..self.len()).filter(|&i| in_degree[i] == 0).collect();
+        let mut order = Vec::with_capacity(self.len());
+
+        while let Some(id) = queue.pop_front() {
+            order.push(id);
+
+            for (child, _) in &self.forward[id] {
+                in_degree[*child] -= 1;
+                if in_degree[*child] == 0 {
+                    queue.push_back(*child);
+                }
+            }
+        }
+
+        match (0..self.len()).find(|&i| in_degree[i] > 0) {
+            Some(id) => Err(CycleError(id)),
+            None => Ok(order),
+        }
+    }
+
+    /// Path from `from` to `to` with the fewest edges.
+    pub fn shortest_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let mut prev: HashMap<usize, usize> = HashMap::new();
+        let mut queue = VecDeque::from(vec![from]);
+
+        while let Some(id) = queue.pop_front() {
+            if id == to {
+                let mut path = vec![to];
+                while let Some(&parent) = prev.get(path.last().unwrap()) {
+                    path.push(parent);
+                }
+                path.reverse();
+
+                return Some(path);
+            }
+
+            for (child, _) in &self.forward[id] {
+                if *child != from && !prev.contains_key(child) {
+                    prev.insert(*child, id);
+                    queue.push_back(*child);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Every path from `from` to `to` not visiting a node twice. Parallel edges between two nodes
+    /// only give one path.
+    pub fn simple_paths(&self, from: usize, to: usize) -> Vec<Vec<usize>> {
+        let mut paths = Vec::new();
+        self.walk_paths(to, &mut vec![from], &mut paths);
+
+        paths
+    }
+
+    fn walk_paths(&self, to: usize, stack: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+        let id = *stack.last().unwrap();
+        if id == to {
+            paths.push(stack.clone());
+            return;
+        }
+
+        let edges = &self.forward[id];
+        for (i, (child, _)) in edges.iter().enumerate() {
+            let parallel = edges[..i].iter().any(|(other, _)| other == child);
+            if !parallel && !stack.contains(child) {
+                stack.push(*child);
+                self.walk_paths(to, stack, paths);
+                stack.pop();
+            }
+        }
+    }
+}
+
+impl<N, W> Graph<N, W>
+where
+    N: Hash + Eq + Clone,
+    W: Copy + Zero + One + CheckedAdd + CheckedMul,
+{
+    /// Sum, over every path leaving `start`, of the product of its weights. Results are kept in
+    /// `cache` so callers can reuse them between calls, a cached node must have all its
+    /// descendants cached as well.
+    pub fn path_product_sum(
+        &self,
+        start: usize,
+        cache: &mut HashMap<usize, W>,
+    ) -> Result<W, PathSumError> {
+        let mut in_progress: HashSet<usize> = HashSet::new();
+        let mut stack = vec![(start, false)];
+
+        while let Some((id, expanded)) = stack.pop() {
+            if cache.contains_key(&id) {
+                continue;
+            }
+
+            if expanded {
+                let total = self.forward[id]
+                    .iter()
+                    .try_fold(W::zero(), |sum, (child, w)| {
+                        let inside = W::one().checked_add(&cache[child])?;
+                        sum.checked_add(&w.checked_mul(&inside)?)
+                    })
+                    .ok_or(PathSumError::Overflow)?;
+
+                in_progress.remove(&id);
+                cache.insert(id, total);
+            } else {
+                in_progress.insert(id);
+                stack.push((id, true));
+
+                for (child, _) in &self.forward[id] {
+                    if in_progress.contains(child) {
+                        return Err(PathSumError::Cycle(*child));
+                    }
+                    stack.push((*child, false));
+                }
+            }
+        }
+
+        Ok(cache[&start])
+    }
+}
+
+pub struct Dfs<'a, N, W> {
+    graph: &'a Graph<N, W>,
+    direction: Direction,
+    stack: Vec<usize>,
+    seen: HashSet<usize>,
+}
+
+impl<'a, N: Hash + Eq + Clone, W: Copy> Iterator for Dfs<'a, N, W> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while let Some(id) = self.stack.pop() {
+            if self.seen.insert(id) {
+                let edges = self.graph.edges(id, self.direction);
+                self.stack
+                    .extend(edges.iter().rev().map(|(child, _)| *child));
+
+                return Some(id);
+            }
+        }
+
+        None
+    }
+}
+
+pub struct Bfs<'a, N, W> {
+    graph: &'a Graph<N, W>,
+    direction: Direction,
+    queue: VecDeque<usize>,
+    seen: HashSet<usize>,
+}
+
+impl<'a, N: Hash + Eq + Clone, W: Copy> Iterator for Bfs<'a, N, W> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let id = self.queue.pop_front()?;
+
+        for (child, _) in self.graph.edges(id, self.direction) {
+            if self.seen.insert(*child) {
+                self.queue.push_back(*child);
+            }
+        }
+
+        Some(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Graph<&'static str, u32> {
+        let mut graph = Graph::new();
+        let edges = [("a", "b", 2), ("a", "c", 3), ("b", "d", 4), ("c", "d", 5)];
+
+        for (from, to, w) in edges.iter() {
+            let from = graph.add_node(*from);
+            let to = graph.add_node(*to);
+            graph.add_edge(from, to, *w);
+        }
+
+        graph
+    }
+
+    #[test]
+    fn traversals() {
+        let graph = sample();
+        let id = |label| graph.node(label).unwrap();
+        let labels = |ids: Vec<usize>| ids.into_iter().map(|i| *graph.label(i)).collect::<Vec<_>>();
+
+        assert_eq!(
+            labels(graph.dfs(id("a"), Direction::Forward).collect()),
+            vec!["a", "b", "d", "c"]
+        );
+        assert_eq!(
+            labels(graph.bfs(id("a"), Direction::Forward).collect()),
+            vec!["a", "b", "c", "d"]
+        );
+        assert_eq!(graph.reachable(id("d"), Direction::Reverse).len(), 3);
+        assert_eq!(
+            labels(graph.topological_sort().unwrap()),
+            vec!["a", "b", "c", "d"]
+        );
+        assert_eq!(
+            labels(graph.shortest_path(id("a"), id("d")).unwrap()),
+            vec!["a", "b", "d"]
+        );
+        assert_eq!(graph.simple_paths(id("a"), id("d")).len(), 2);
+    }
+
+    #[test]
+    fn path_products() {
+        let mut graph = sample();
+        let mut cache = HashMap::new();
+        let a = graph.node("a").unwrap();
+        let d = graph.node("d").unwrap();
+
+        // a -> b, a -> c, a -> b -> d, a -> c -> d
+        assert_eq!(
+            graph.path_product_sum(a, &mut cache),
+            Ok(2 + 3 + 2 * 4 + 3 * 5)
+        );
+
+        graph.add_edge(d, a, 1);
+        assert_eq!(graph.topological_sort(), Err(CycleError(a)));
+        assert_eq!(
+            graph.path_product_sum(a, &mut HashMap::new()),
+            Err(PathSumError::Cycle(a))
+        );
+
+        graph.remove_edges(d);
+        assert_eq!(graph.reachable(a, Direction::Reverse).len(), 0);
+    }
+
+    #[test]
+    fn path_product_overflow() {
+        let mut graph: Graph<&str, u8> = Graph::new();
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+        graph.add_edge(a, b, 150);
+        graph.add_edge(a, c, 150);
+
+        let mut cache = HashMap::new();
+        assert_eq!(graph.path_product_sum(b, &mut cache), Ok(0));
+        assert_eq!(
+            graph.path_product_sum(a, &mut cache),
+            Err(PathSumError::Overflow)
+        );
+        assert!(!cache.contains_key(&a));
+
+        graph.remove_edges(a);
+        graph.add_edge(a, b, 100);
+        graph.add_edge(a, c, 155);
+        assert_eq!(graph.path_product_sum(a, &mut cache), Ok(255));
+    }
+
+    #[test]
+    fn parallel_edges() {
+        let mut graph = sample();
+        let a = graph.node("a").unwrap();
+        let b = graph.node("b").unwrap();
+        let d = graph.node("d").unwrap();
+
+        graph.add_edge(a, b, 7);
+        assert_eq!(graph.weight(a, b), Some(2));
+        assert_eq!(graph.simple_paths(a, d).len(), 2);
+        assert_eq!(graph.simple_paths(a, b), vec![vec![a, b]]);
+        assert_eq!(
+            graph.path_product_sum(a, &mut HashMap::new()),
+            Ok(2 + 7 + 3 + (2 + 7) * 4 + 3 * 5)
+        );
+    }
+}
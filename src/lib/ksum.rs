@@ -0,0 +1,391 @@
+use itertools::Itertools;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::{cmp::Ordering, collections::HashMap, convert::TryFrom};
+
+use crate::seq;
+
+/// Finds `k` entries at distinct indices adding up to `target`, returned as `(index, value)`
+/// pairs sorted by index. Uses hashing for `k = 2`, sort and two pointers for `k = 3` and
+/// meet-in-the-middle over index combinations for larger `k`.
+pub fn k_sum(input: &[i32], target: i32, k: usize) -> Option<Vec<(usize, i32)>> {
+    let target = target as i64;
+    let mut indices = match k {
+        0 if target == 0 => Some(Vec::new()),
+        0 => None,
+        1 => input
+            .iter()
+            .position(|&x| x as i64 == target)
+            .map(|i| vec![i]),
+        2 => two_sum(input, target),
+        3 => three_sum(input, target),
+        _ => meet_in_the_middle(input, target, k),
+    }?;
+
+    indices.sort_unstable();
+    Some(indices.into_iter().map(|i| (i, input[i])).collect())
+}
+
+fn two_sum(input: &[i32], target: i64) -> Option<Vec<usize>> {
+    let target = i32::try_from(target).ok()?;
+    let (i, j) = seq::pair_sum(input, target)?;
+
+    Some(vec![i, j])
+}
+
+/// Positions of the entries sorted by value, ties kept in index order.
+fn sorted_positions(input: &[i32]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..input.len()).collect();
+    order.sort_by_key(|&i| input[i]);
+    order
+}
+
+fn three_sum(input: &[i32], target: i64) -> Option<Vec<usize>> {
+    let order = sorted_positions(input);
+
+    (0..order.len().saturating_sub(2)).find_map(|a| three_sum_from(input, &order, a, target))
+}
+
+/// Two pointers over the positions after `a` looking for a pair completing `order[a]`.
+fn three_sum_from(input: &[i32], order: &[usize], a: usize, target: i64) -> Option<Vec<usize>> {
+    let value = |p: usize| input[order[p]] as i64;
+    let (mut lo, mut hi) = (a + 1, order.len() - 1);
+
+    while lo < hi {
+        match (value(a) + value(lo) + value(hi)).cmp(&target) {
+            Ordering::Equal => return Some(vec![order[a], order[lo], order[hi]]),
+            Ordering::Less => lo += 1,
+            Ordering::Greater => hi -= 1,
+        }
+    }
+
+    None
+}
+
+/// Sums of every combination of `left` indices, each sum mapped to its combinations in
+/// lexicographic order.
+fn half_sums(input: &[i32], left: usize) -> HashMap<i64, Vec<Vec<usize>>> {
+    let mut halves: HashMap<i64, Vec<Vec<usize>>> = HashMap::new();
+    for xs in (0..input.len()).combinations(left) {
+        let sum = xs.iter().map(|&i| input[i] as i64).sum();
+        halves.entry(sum).or_default().push(xs);
+    }
+
+    halves
+}
+
+/// Completes the combination `xs` with one of `halves` not sharing any index.
+fn complete_half(
+    input: &[i32],
+    halves: &HashMap<i64, Vec<Vec<usize>>>,
+    xs: Vec<usize>,
+    target: i64,
+) -> Option<Vec<usize>> {
+    let sum: i64 = xs.iter().map(|&i| input[i] as i64).sum();
+    let ys = halves
+        .get(&(target - sum))?
+        .iter()
+        .find(|ys| ys.iter().all(|i| !xs.contains(i)))?;
+
+    Some(xs.into_iter().chain(ys.iter().cloned()).collect())
+}
+
+fn meet_in_the_middle(input: &[i32], target: i64, k: usize) -> Option<Vec<usize>> {
+    let halves = half_sums(input, k / 2);
+
+    (0..input.len())
+        .combinations(k - k / 2)
+        .find_map(|xs| complete_half(input, &halves, xs, target))
+}
+
+/// Same as [`k_sum`], with the candidate search spread over the rayon thread pool. Always
+/// returns the same entries as the sequential version.
+#[cfg(feature = "parallel")]
+pub fn par_k_sum(input: &[i32], target: i32, k: usize) -> Option<Vec<(usize, i32)>> {
+    let target = target as i64;
+    let mut indices = match k {
+        0 | 1 => return k_sum(input, target as i32, k),
+        2 => par_two_sum(input, target),
+        3 => par_three_sum(input, target),
+        _ => par_meet_in_the_middle(input, target, k),
+    }?;
+
+    indices.sort_unstable();
+    Some(indices.into_iter().map(|i| (i, input[i])).collect())
+}
+
+/// Finds the first `j` with an earlier complement, paired with the complement's first index,
+/// which is what [`two_sum`] returns.
+#[cfg(feature = "parallel")]
+fn par_two_sum(input: &[i32], target: i64) -> Option<Vec<usize>> {
+    let mut first: HashMap<i64, usize> = HashMap::new();
+    for (i, &x) in input.iter().enumerate() {
+        first.entry(x as i64).or_insert(i);
+    }
+
+    input.par_iter().enumerate().find_map_first(|(j, &x)| {
+        let i = *first.get(&(target - x as i64))?;
+        if i < j {
+            Some(vec![i, j])
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(feature = "parallel")]
+fn par_three_sum(input: &[i32], target: i64) -> Option<Vec<usize>> {
+    let mut order: Vec<usize> = (0..input.len()).collect();
+    order.par_sort_by_key(|&i| input[i]);
+
+    (0..order.len().saturating_sub(2))
+        .into_par_iter()
+        .find_map_first(|a| three_sum_from(input, &order, a, target))
+}
+
+/// Splits the combinations by their first index so the lexicographic order, and therefore the
+/// first match, is the same as in [`meet_in_the_middle`].
+#[cfg(feature = "parallel")]
+fn par_meet_in_the_middle(input: &[i32], target: i64, k: usize) -> Option<Vec<usize>> {
+    let halves = half_sums(input, k / 2);
+    let right = k - k / 2;
+
+    (0..input.len()).into_par_iter().find_map_first(|first| {
+        (first + 1..input.len())
+            .combinations(right - 1)
+            .map(|rest| Some(first).into_iter().chain(rest).collect())
+            .find_map(|xs| complete_half(input, &halves, xs, target))
+    })
+}
+
+/// A set of distinct indices adding up to the target, along with each distinct value and how
+/// many times it appears in the combination.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KSumCombination {
+    pub indices: Vec<usize>,
+    pub multiplicities: Vec<(i32, usize)>,
+}
+
+/// Iterator over every combination of `k` distinct indices adding up to `target`.
+pub fn k_sum_all(input: &[i32], target: i32, k: usize) -> KSums<'_> {
+    let mut order: Vec<usize> = (0..input.len()).collect();
+    order.sort_unstable_by_key(|&i| input[i]);
+
+    let prefix = order.iter().fold(vec![0], |mut prefix, &i| {
+        prefix.push(prefix.last().unwrap() + input[i] as i64);
+        prefix
+    });
+
+    KSums {
+        input,
+        order,
+        prefix,
+        target: target as i64,
+        k,
+        picks: Vec::new(),
+        sum: 0,
+        cursors: if k == 0 { Vec::new() } else { vec![0] },
+        empty_match: k == 0 && target == 0,
+    }
+}
+
+/// Number of combinations [`k_sum_all`] would yield, counted by dynamic programming over
+/// `(entries picked, sum)` without enumerating them.
+pub fn k_sum_count(input: &[i32], target: i32, k: usize) -> u64 {
+    let mut counts: Vec<HashMap<i64, u64>> = vec![HashMap::new(); k + 1];
+    counts[0].insert(0, 1);
+
+    for &x in input {
+        for picked in (1..=k).rev() {
+            let (lower, upper) = counts.split_at_mut(picked);
+            for (sum, count) in &lower[picked - 1] {
+                *upper[0].entry(sum + x as i64).or_insert(0) += count;
+            }
+        }
+    }
+
+    counts[k].get(&(target as i64)).copied().unwrap_or(0)
+}
+
+/// Depth-first search over positions of the entries sorted by value, pruning branches whose
+/// smallest or largest reachable sum misses the target.
+pub struct KSums<'a> {
+    input: &'a [i32],
+    order: Vec<usize>,
+    prefix: Vec<i64>,
+    target: i64,
+    k: usize,
+    picks: Vec<usize>,
+    sum: i64,
+    cursors: Vec<usize>,
+    empty_match: bool,
+}
+
+impl<'a> KSums<'a> {
+    fn value(&self, pos: usize) -> i64 {
+        self.input[self.order[pos]] as i64
+    }
+
+    fn feasible(&self, pos: usize, remaining: usize) -> bool {
+        let n = self.order.len();
+        if pos + remaining > n {
+            return false;
+        }
+
+        let need = self.target - self.sum;
+        let min = self.prefix[pos + remaining] - self.prefix[pos];
+        let max = self.prefix[n] - self.prefix[n - remaining];
+
+        min <= need && need <= max
+    }
+
+    fn combination(&self, last: usize) -> KSumCombination {
+        let positions: Vec<usize> = self.picks.iter().cloned().chain(Some(last)).collect();
+
+        let mut indices: Vec<usize> = positions.iter().map(|&p| self.order[p]).collect();
+        indices.sort_unstable();
+
+        let mut multiplicities: Vec<(i32, usize)> = Vec::new();
+        for &p in &positions {
+            let value = self.input[self.order[p]];
+            match multiplicities.last_mut() {
+                Some((last, count)) if *last == value => *count += 1,
+                _ => multiplicities.push((value, 1)),
+            }
+        }
+
+        KSumCombination {
+            indices,
+            multiplicities,
+        }
+    }
+}
+
+impl<'a> Iterator for KSums<'a> {
+    type Item = KSumCombination;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.empty_match {
+            self.empty_match = false;
+            return Some(KSumCombination {
+                indices: Vec::new(),
+                multiplicities: Vec::new(),
+            });
+        }
+
+        while let Some(&pos) = self.cursors.last() {
+            let remaining = self.k - self.picks.len();
+
+            if !self.feasible(pos, remaining) {
+                self.cursors.pop();
+                if let Some(p) = self.picks.pop() {
+                    self.sum -= self.value(p);
+                }
+                continue;
+            }
+
+            *self.cursors.last_mut().unwrap() = pos + 1;
+
+            if remaining == 1 {
+                if self.sum + self.value(pos) == self.target {
+                    return Some(self.combination(pos));
+                }
+            } else {
+                self.picks.push(pos);
+                self.sum += self.value(pos);
+                self.cursors.push(pos + 1);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distinct_indices() {
+        assert_eq!(k_sum(&[1010, 5], 2020, 2), None);
+        assert_eq!(
+            k_sum(&[1010, 5, 1010], 2020, 2),
+            Some(vec![(0, 1010), (2, 1010)])
+        );
+        assert_eq!(k_sum(&[700, 1, 620], 2020, 3), None);
+        assert_eq!(
+            k_sum(&[700, 1, 700, 620], 2020, 3),
+            Some(vec![(0, 700), (2, 700), (3, 620)])
+        );
+    }
+
+    #[test]
+    fn any_k() {
+        let input = [1721, 979, 366, 299, 675, 1456];
+
+        assert_eq!(k_sum(&input, 1721, 1), Some(vec![(0, 1721)]));
+        assert_eq!(k_sum(&input, 2020, 2), Some(vec![(0, 1721), (3, 299)]));
+        assert_eq!(
+            k_sum(&input, 2020, 3),
+            Some(vec![(1, 979), (2, 366), (4, 675)])
+        );
+        assert_eq!(
+            k_sum(&input, 979 + 366 + 299 + 1456, 4),
+            Some(vec![(1, 979), (2, 366), (3, 299), (5, 1456)])
+        );
+        assert_eq!(
+            k_sum(&input, input.iter().sum(), 6).map(|xs| xs.len()),
+            Some(6)
+        );
+        assert_eq!(k_sum(&input, 1, 4), None);
+        assert_eq!(k_sum(&input, 0, 0), Some(vec![]));
+    }
+
+    #[test]
+    fn all_combinations() {
+        let input = [3, 1, 2, 1, 3];
+
+        let pairs: Vec<Vec<usize>> = k_sum_all(&input, 4, 2).map(|c| c.indices).collect();
+        assert_eq!(pairs.len(), 4);
+        assert!(pairs.contains(&vec![0, 1]));
+        assert!(pairs.contains(&vec![3, 4]));
+
+        let triples: Vec<KSumCombination> = k_sum_all(&input, 5, 3).collect();
+        assert_eq!(triples.len(), 2);
+        assert!(triples
+            .iter()
+            .all(|c| c.multiplicities == vec![(1, 2), (3, 1)]));
+
+        assert_eq!(k_sum_all(&input, 0, 0).count(), 1);
+        assert_eq!(k_sum_all(&input, 100, 2).count(), 0);
+    }
+
+    #[test]
+    fn count_matches_brute_force() {
+        let input = [5, -2, 7, 3, 3, 0, 8, -2, 4, 1, 6, 3];
+
+        for k in 1..=5 {
+            for target in -4..20 {
+                let expected = (0..input.len())
+                    .combinations(k)
+                    .filter(|xs| xs.iter().map(|&i| input[i]).sum::<i32>() == target)
+                    .count();
+
+                assert_eq!(k_sum_all(&input, target, k).count(), expected);
+                assert_eq!(k_sum_count(&input, target, k), expected as u64);
+            }
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_matches_sequential() {
+        let input = [5, -2, 7, 3, 3, 0, 8, -2, 4, 1, 6, 3];
+
+        for k in 0..=5 {
+            for target in -4..20 {
+                assert_eq!(par_k_sum(&input, target, k), k_sum(&input, target, k));
+            }
+        }
+    }
+}
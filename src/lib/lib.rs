@@ -1,8 +1,14 @@
 #![feature(destructuring_assignment)]
 
-mod puzzles;
+pub mod batch;
+pub mod generators;
+pub mod graph;
+pub mod ksum;
+pub mod passport;
+pub mod puzzles;
+pub mod schema;
+pub mod seq;
 
-use itertools::Itertools;
 use std::{collections::HashMap, error::Error, fmt};
 
 pub fn decode_lines<'a, I, T, F>(input: I, decode: F) -> impl Iterator<Item = T> + 'a
@@ -13,28 +19,6 @@ where
     input.into().split("\n").map(move |a| decode(a))
 }
 
-pub fn decode_lines_group<'a, I, T, F>(input: I, decode: F) -> impl Iterator<Item = T> + 'a
-where
-    I: Into<&'a str>,
-    F: Fn(&'a str) -> T + 'a,
-{
-    input.into().split("\n\n").map(move |a| decode(a))
-}
-
-pub fn self_cross_product(input: &[i32], dims: usize) -> impl Iterator<Item = Vec<i32>> + '_ {
-    (0..dims)
-        .map(|_| input.iter().cloned())
-        .multi_cartesian_product()
-}
-
-pub fn compute_product(xs: &[i32]) -> i64 {
-    xs.iter().map(|x| *x as i64).product::<i64>()
-}
-
-pub fn check_sum_equals(xs: &[i32], target: i32) -> bool {
-    xs.iter().sum::<i32>() == target
-}
-
 pub fn take_half(range: (i32, i32), section: char) -> (i32, i32) {
     match section {
         'F' | 'L' => (range.0, range.0 + (range.1 - range.0) / 2),
@@ -60,10 +44,17 @@ pub fn frequencies(s: &str) -> impl Iterator<Item = (char, usize)> {
     freqs.into_iter()
 }
 
-pub fn concat_vec<T>(a: Vec<T>, b: Vec<T>) -> impl Iterator<Item = T> {
-    a.into_iter().chain(b.into_iter())
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverflowError;
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Arithmetic overflow")
+    }
 }
 
+impl Error for OverflowError {}
+
 #[derive(Debug)]
 struct PuzzleError;
 
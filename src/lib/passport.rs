@@ -0,0 +1,365 @@
+use std::{error::Error, fmt, str::FromStr};
+
+/// A field value that didn't parse as its type, with the reason why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFieldError {
+    pub value: String,
+    pub reason: &'static str,
+}
+
+impl ParseFieldError {
+    fn new(value: &str, reason: &'static str) -> Self {
+        Self {
+            value: value.to_string(),
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for ParseFieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' {}", self.value, self.reason)
+    }
+}
+
+impl Error for ParseFieldError {}
+
+/// A passport field as read: the typed value along with the text it was read from, or the raw
+/// text if it doesn't parse.
+///
+/// Keeping the raw text means a field that is present but malformed still counts as present,
+/// and that rules written against the text, such as a [`Schema`](crate::schema::Schema), see
+/// the value as it was written rather than its canonical form.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field<T> {
+    Parsed { value: T, raw: String },
+    Unparsed(ParseFieldError),
+}
+
+impl<T: FromStr<Err = ParseFieldError>> Field<T> {
+    pub fn read(value: &str) -> Self {
+        Self::read_with(value, str::parse)
+    }
+}
+
+impl<T> Field<T> {
+    /// Reads `value` with `parse`, for types with more than one way of parsing them.
+    pub fn read_with<F>(value: &str, parse: F) -> Self
+    where
+        F: FnOnce(&str) -> Result<T, ParseFieldError>,
+    {
+        match parse(value) {
+            Ok(parsed) => Field::Parsed {
+                value: parsed,
+                raw: value.to_string(),
+            },
+            Err(error) => Field::Unparsed(error),
+        }
+    }
+
+    pub fn parsed(&self) -> Option<&T> {
+        match self {
+            Field::Parsed { value, .. } => Some(value),
+            Field::Unparsed(_) => None,
+        }
+    }
+
+    pub fn error(&self) -> Option<&ParseFieldError> {
+        match self {
+            Field::Parsed { .. } => None,
+            Field::Unparsed(error) => Some(error),
+        }
+    }
+
+    /// The text the field was read from.
+    pub fn raw(&self) -> &str {
+        match self {
+            Field::Parsed { raw, .. } => raw,
+            Field::Unparsed(error) => &error.value,
+        }
+    }
+}
+
+/// Parsed values show in their canonical form, unparsed ones as they were read.
+impl<T: fmt::Display> fmt::Display for Field<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Field::Parsed { value, .. } => write!(f, "{}", value),
+            Field::Unparsed(error) => write!(f, "{}", error.value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Unit {
+    Cm,
+    In,
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unit::Cm => write!(f, "cm"),
+            Unit::In => write!(f, "in"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Height {
+    pub value: u32,
+    pub unit: Unit,
+}
+
+impl FromStr for Height {
+    type Err = ParseFieldError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, unit) = if let Some(value) = s.strip_suffix("cm") {
+            (value, Unit::Cm)
+        } else if let Some(value) = s.strip_suffix("in") {
+            (value, Unit::In)
+        } else {
+            return Err(ParseFieldError::new(s, "has no unit, expected cm or in"));
+        };
+
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseFieldError::new(s, "is not a whole number of cm or in"));
+        }
+        let value = value
+            .parse()
+            .map_err(|_| ParseFieldError::new(s, "is too large a height"))?;
+
+        Ok(Self { value, unit })
+    }
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.value, self.unit)
+    }
+}
+
+/// How hair colors are read. The puzzle spec only allows `#rrggbb` with lowercase digits, the
+/// lenient mode also takes uppercase digits and the `#rgb` shorthand.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    #[default]
+    Lenient,
+    Strict,
+}
+
+/// An RGB color, written `#rrggbb` or in the `#rgb` shorthand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HairColor(pub [u8; 3]);
+
+impl HairColor {
+    pub fn parse(s: &str, mode: ColorMode) -> Result<Self, ParseFieldError> {
+        let invalid = || match mode {
+            ColorMode::Lenient => ParseFieldError::new(s, "is not a #rrggbb or #rgb color"),
+            ColorMode::Strict => ParseFieldError::new(s, "is not a lowercase #rrggbb color"),
+        };
+        let hex = s.strip_prefix('#').ok_or_else(invalid)?;
+        if mode == ColorMode::Strict && hex.bytes().any(|b| b.is_ascii_uppercase()) {
+            return Err(invalid());
+        }
+        let digits = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(invalid)?;
+
+        let rgb = match (mode, &digits[..]) {
+            (ColorMode::Lenient, &[r, g, b]) => [r * 0x11, g * 0x11, b * 0x11],
+            (_, &[r1, r2, g1, g2, b1, b2]) => [r1 << 4 | r2, g1 << 4 | g2, b1 << 4 | b2],
+            _ => return Err(invalid()),
+        };
+
+        Ok(Self(rgb))
+    }
+}
+
+impl FromStr for HairColor {
+    type Err = ParseFieldError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, ColorMode::Lenient)
+    }
+}
+
+impl fmt::Display for HairColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EyeColor {
+    Amb,
+    Blu,
+    Brn,
+    Gry,
+    Grn,
+    Hzl,
+    Oth,
+}
+
+impl EyeColor {
+    pub const ALL: [EyeColor; 7] = [
+        EyeColor::Amb,
+        EyeColor::Blu,
+        EyeColor::Brn,
+        EyeColor::Gry,
+        EyeColor::Grn,
+        EyeColor::Hzl,
+        EyeColor::Oth,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            EyeColor::Amb => "amb",
+            EyeColor::Blu => "blu",
+            EyeColor::Brn => "brn",
+            EyeColor::Gry => "gry",
+            EyeColor::Grn => "grn",
+            EyeColor::Hzl => "hzl",
+            EyeColor::Oth => "oth",
+        }
+    }
+}
+
+impl FromStr for EyeColor {
+    type Err = ParseFieldError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EyeColor::ALL
+            .iter()
+            .copied()
+            .find(|color| color.code() == s)
+            .ok_or_else(|| ParseFieldError::new(s, "is not an eye color"))
+    }
+}
+
+impl fmt::Display for EyeColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// A passport id, kept as its digits so leading zeros survive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PassportId(String);
+
+impl PassportId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for PassportId {
+    type Err = ParseFieldError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseFieldError::new(s, "is not a number"));
+        }
+
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl fmt::Display for PassportId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T>(s: &str) -> String
+    where
+        T: FromStr<Err = ParseFieldError> + fmt::Display,
+    {
+        s.parse::<T>().unwrap().to_string()
+    }
+
+    #[test]
+    fn typed_fields() {
+        assert_eq!(
+            "183cm".parse::<Height>(),
+            Ok(Height {
+                value: 183,
+                unit: Unit::Cm
+            })
+        );
+        assert_eq!(round_trip::<Height>("59in"), "59in");
+        assert_eq!(round_trip::<Height>("0190cm"), "190cm");
+
+        assert_eq!(
+            "#cfa07d".parse::<HairColor>(),
+            Ok(HairColor([0xcf, 0xa0, 0x7d]))
+        );
+        assert_eq!(round_trip::<HairColor>("#CFA07D"), "#cfa07d");
+        assert_eq!(round_trip::<HairColor>("#1aF"), "#11aaff");
+
+        assert_eq!("hzl".parse::<EyeColor>(), Ok(EyeColor::Hzl));
+        for color in EyeColor::ALL.iter() {
+            assert_eq!(round_trip::<EyeColor>(color.code()), color.code());
+        }
+
+        assert_eq!(round_trip::<PassportId>("000000001"), "000000001");
+        assert_eq!(round_trip::<PassportId>("0123456789"), "0123456789");
+    }
+
+    #[test]
+    fn parse_errors() {
+        let reason = |s: &str, error: ParseFieldError| {
+            assert_eq!(error.value, s);
+            error.to_string()
+        };
+
+        assert_eq!(
+            reason("190", "190".parse::<Height>().unwrap_err()),
+            "'190' has no unit, expected cm or in"
+        );
+        assert!("+5cm".parse::<Height>().is_err());
+        assert!("cm".parse::<Height>().is_err());
+        assert!("99999999999in".parse::<Height>().is_err());
+
+        for hcl in &["123abc", "#123abz", "#1234", "#", "#+1a"] {
+            assert!(hcl.parse::<HairColor>().is_err(), "{}", hcl);
+        }
+
+        assert_eq!(
+            reason("wat", "wat".parse::<EyeColor>().unwrap_err()),
+            "'wat' is not an eye color"
+        );
+        assert!("BRN".parse::<EyeColor>().is_err());
+
+        assert!("".parse::<PassportId>().is_err());
+        assert!("0123abc".parse::<PassportId>().is_err());
+
+        let strict = |s: &str| HairColor::parse(s, ColorMode::Strict);
+        assert_eq!(strict("#cfa07d"), Ok(HairColor([0xcf, 0xa0, 0x7d])));
+        assert_eq!(
+            strict("#1af").unwrap_err().to_string(),
+            "'#1af' is not a lowercase #rrggbb color"
+        );
+        assert!(strict("#CFA07D").is_err());
+
+        let field: Field<Height> = Field::read("190");
+        assert_eq!(field.parsed(), None);
+        assert_eq!(field.to_string(), "190");
+        assert_eq!(field.raw(), "190");
+
+        let field: Field<Height> = Field::read("0190cm");
+        assert_eq!(field.to_string(), "190cm");
+        assert_eq!(field.raw(), "0190cm");
+        let field = Field::read_with("#1AF", |s| HairColor::parse(s, ColorMode::Strict));
+        assert_eq!(field.raw(), "#1AF");
+        assert!(field.error().is_some());
+    }
+}
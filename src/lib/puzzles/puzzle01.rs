@@ -1,4 +1,10 @@
-use crate::{check_sum_equals, compute_product, self_cross_product};
+use std::error::Error;
+
+#[cfg(not(feature = "parallel"))]
+use crate::ksum::k_sum;
+#[cfg(feature = "parallel")]
+use crate::ksum::par_k_sum as k_sum;
+use crate::seq::compute_product;
 
 #[allow(dead_code)] // Allow dead until CLI is ready
 pub fn solve_puzzle(input: &[i32]) -> (i64, i64) {
@@ -9,17 +15,11 @@ pub fn solve_puzzle(input: &[i32]) -> (i64, i64) {
 }
 
 #[allow(dead_code)] // Allow dead until CLI is ready
-fn solve_part(input: &[i32], target: i32, dims: usize) -> Option<i64> {
-    let result: Vec<i64> = self_cross_product(input, dims)
-        .filter(|xs| check_sum_equals(&xs, target))
-        .take(1)
-        .map(|xs| compute_product(&xs))
-        .collect();
-
-    match result.get(0) {
-        Some(x) => Some(*x),
-        None => None,
-    }
+fn solve_part(input: &[i32], target: i32, dims: usize) -> Result<i64, Box<dyn Error>> {
+    let entries = k_sum(input, target, dims).ok_or("Solution not found")?;
+    let values: Vec<i32> = entries.into_iter().map(|(_, x)| x).collect();
+
+    Ok(compute_product(&values)?)
 }
 
 #[cfg(test)]
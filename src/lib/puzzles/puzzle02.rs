@@ -1,63 +1,434 @@
-use std::{error::Error, ops::BitXor};
+use regex::Regex;
+use serde::Serialize;
+use std::{collections::BTreeMap, error::Error, fmt, ops::BitXor};
 
 use crate::decode_lines;
 
 pub fn solve_part1<'a, I: Into<&'a str>>(input: I) -> i32 {
     decode_lines(input, |s| parse_entry(s).unwrap())
-        .filter(|&(min, max, target, data)| verify_part1(&min, &max, &target, data))
+        .filter(|&(min, max, letter, data)| CountPolicy { min, max, letter }.verify(data))
         .count() as i32
 }
 
 pub fn solve_part2<'a, I: Into<&'a str>>(input: I) -> i32 {
     decode_lines(input, |s| parse_entry(s).unwrap())
-        .filter(|&(min, max, target, data)| verify_part2(&min, &max, &target, data))
+        .filter(|&(first, second, letter, data)| {
+            PositionPolicy {
+                first,
+                second,
+                letter,
+            }
+            .verify(data)
+        })
         .count() as i32
 }
 
+/// Displays as `<kind> <spec>`, the registry name and the spec it was parsed from.
+pub trait PasswordPolicy: fmt::Display {
+    /// Whether `password` complies with the policy.
+    fn verify(&self, password: &str) -> bool;
+
+    /// Why `password` doesn't comply with the policy, `None` if it does.
+    fn explain(&self, password: &str) -> Option<String> {
+        if self.verify(password) {
+            None
+        } else {
+            Some(format!("doesn't comply with {}", self))
+        }
+    }
+}
+
+/// `letter` must appear between `min` and `max` times.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountPolicy {
+    pub min: usize,
+    pub max: usize,
+    pub letter: char,
+}
+
+impl PasswordPolicy for CountPolicy {
+    fn verify(&self, password: &str) -> bool {
+        verify_part1(&self.min, &self.max, &self.letter, password)
+    }
+
+    fn explain(&self, password: &str) -> Option<String> {
+        if self.verify(password) {
+            return None;
+        }
+
+        Some(format!(
+            "found {} of '{}', need {}–{}",
+            password.matches(self.letter).count(),
+            self.letter,
+            self.min,
+            self.max
+        ))
+    }
+}
+
+impl fmt::Display for CountPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "count {}-{} {}", self.min, self.max, self.letter)
+    }
+}
+
+/// `letter` must be at exactly one of the 1-based positions `first` and `second`. Positions
+/// outside the password count as not holding `letter`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionPolicy {
+    pub first: usize,
+    pub second: usize,
+    pub letter: char,
+}
+
+impl PasswordPolicy for PositionPolicy {
+    fn verify(&self, password: &str) -> bool {
+        verify_part2(&self.first, &self.second, &self.letter, password)
+    }
+
+    fn explain(&self, password: &str) -> Option<String> {
+        if self.verify(password) {
+            return None;
+        }
+
+        let (a, b, letter) = (self.first, self.second, self.letter);
+        // Not verified, so either both positions hold the letter or neither does.
+        if chars_at(password, a, b).0 == Some(letter) {
+            return Some(format!("positions {} and {} both match '{}'", a, b, letter));
+        }
+
+        let length = password.chars().count();
+        let outside: Vec<String> = [a, b]
+            .iter()
+            .filter(|&&p| p == 0 || p > length)
+            .map(|p| p.to_string())
+            .collect();
+
+        let mut reason = format!("neither position {} nor {} matches '{}'", a, b, letter);
+        if !outside.is_empty() {
+            reason += &format!(
+                ", {} outside the {} characters of the password",
+                outside.join(" and "),
+                length
+            );
+        }
+
+        Some(reason)
+    }
+}
+
+impl fmt::Display for PositionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "positions {}-{} {}",
+            self.first, self.second, self.letter
+        )
+    }
+}
+
+/// The password must match the regex.
+#[derive(Debug, Clone)]
+pub struct RegexPolicy(pub Regex);
+
+impl PasswordPolicy for RegexPolicy {
+    fn verify(&self, password: &str) -> bool {
+        self.0.is_match(password)
+    }
+
+    fn explain(&self, password: &str) -> Option<String> {
+        if self.verify(password) {
+            None
+        } else {
+            Some(format!("doesn't match /{}/", self.0))
+        }
+    }
+}
+
+impl fmt::Display for RegexPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "regex {}", self.0)
+    }
+}
+
+/// The password must have at least this many distinct characters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistinctPolicy(pub usize);
+
+impl DistinctPolicy {
+    fn distinct(password: &str) -> usize {
+        let mut chars: Vec<char> = password.chars().collect();
+        chars.sort_unstable();
+        chars.dedup();
+
+        chars.len()
+    }
+}
+
+impl PasswordPolicy for DistinctPolicy {
+    fn verify(&self, password: &str) -> bool {
+        Self::distinct(password) >= self.0
+    }
+
+    fn explain(&self, password: &str) -> Option<String> {
+        let found = Self::distinct(password);
+        if found >= self.0 {
+            None
+        } else {
+            Some(format!(
+                "found {} distinct characters, need {}",
+                found, self.0
+            ))
+        }
+    }
+}
+
+impl fmt::Display for DistinctPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "distinct {}", self.0)
+    }
+}
+
+/// The password must not contain any of these substrings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForbiddenPolicy(pub Vec<String>);
+
+impl PasswordPolicy for ForbiddenPolicy {
+    fn verify(&self, password: &str) -> bool {
+        self.explain(password).is_none()
+    }
+
+    fn explain(&self, password: &str) -> Option<String> {
+        let found = self.0.iter().find(|s| password.contains(s.as_str()))?;
+
+        Some(format!("contains forbidden '{}'", found))
+    }
+}
+
+impl fmt::Display for ForbiddenPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "forbidden {}", self.0.join(","))
+    }
+}
+
+/// Builds a policy from its textual spec, the part of an entry before the `:`.
+pub type PolicyParser = fn(&str) -> Result<Box<dyn PasswordPolicy>, Box<dyn Error>>;
+
+/// Policy kinds by name. The default registry knows:
+///
+/// - `count`: `1-3 a`, see [`CountPolicy`]
+/// - `positions`: `1-3 a`, see [`PositionPolicy`]
+/// - `regex`: any regex, see [`RegexPolicy`]
+/// - `distinct`: `5`, see [`DistinctPolicy`]
+/// - `forbidden`: `abc,123`, see [`ForbiddenPolicy`]
+pub struct PolicyRegistry {
+    parsers: BTreeMap<String, PolicyParser>,
+}
+
+impl PolicyRegistry {
+    pub fn new() -> Self {
+        Self {
+            parsers: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a policy kind, returns the parser it replaces if `name` was taken.
+    pub fn register(&mut self, name: &str, parser: PolicyParser) -> Option<PolicyParser> {
+        self.parsers.insert(name.to_string(), parser)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.parsers.keys().map(|name| name.as_str())
+    }
+
+    pub fn parse(&self, name: &str, spec: &str) -> Result<Box<dyn PasswordPolicy>, Box<dyn Error>> {
+        let parser = self.parsers.get(name).ok_or_else(|| {
+            let names: Vec<&str> = self.names().collect();
+            format!(
+                "Unknown policy {}, expected one of: {}",
+                name,
+                names.join(", ")
+            )
+        })?;
+
+        parser(spec)
+    }
+}
+
+impl Default for PolicyRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+
+        registry.register("count", |spec| {
+            let (min, max, letter) = parse_policy(spec)?;
+            Ok(Box::new(CountPolicy { min, max, letter }))
+        });
+        registry.register("positions", |spec| {
+            let (first, second, letter) = parse_policy(spec)?;
+            Ok(Box::new(PositionPolicy {
+                first,
+                second,
+                letter,
+            }))
+        });
+        registry.register("regex", |spec| Ok(Box::new(RegexPolicy(Regex::new(spec)?))));
+        registry.register("distinct", |spec| {
+            Ok(Box::new(DistinctPolicy(spec.trim().parse()?)))
+        });
+        registry.register("forbidden", |spec| {
+            let substrings = spec
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect();
+            Ok(Box::new(ForbiddenPolicy(substrings)))
+        });
+
+        registry
+    }
+}
+
+/// Splits a `<policy>: <password>` entry at the last `": "`, since passwords have no spaces
+/// but policies such as regexes may contain colons. Entries without a space after the
+/// separator, as in `1-3 a:abcde`, are split at the first `:`.
+pub fn split_entry(raw: &str) -> Result<(&str, &str), Box<dyn Error>> {
+    let (spec, password) = match raw.rfind(": ") {
+        Some(separator) => (&raw[..separator], &raw[separator + 2..]),
+        None => raw.split_once(':').ok_or("Cannot find entry separator")?,
+    };
+
+    Ok((spec.trim(), password.trim()))
+}
+
+/// Verdict on one entry of a password file. Entries whose policy can't be parsed are invalid,
+/// with the parse error as the reason.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditEntry {
+    pub line: usize,
+    pub policy: Option<String>,
+    pub password: String,
+    pub valid: bool,
+    pub reason: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(line: usize, policy: &dyn PasswordPolicy, password: &str) -> Self {
+        let reason = policy.explain(password);
+
+        Self {
+            line,
+            policy: Some(policy.to_string()),
+            password: password.to_string(),
+            valid: reason.is_none(),
+            reason,
+        }
+    }
+
+    /// Audits a `<policy>: <password>` entry, parsing its policy as `kind`.
+    pub fn parse(line: usize, raw: &str, registry: &PolicyRegistry, kind: &str) -> Self {
+        match parse_entry_with(raw, |spec| registry.parse(kind, spec)) {
+            Ok((policy, password)) => Self::new(line, policy.as_ref(), password),
+            Err(e) => Self {
+                line,
+                policy: None,
+                password: raw.trim().to_string(),
+                valid: false,
+                reason: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}",
+            self.line,
+            if self.valid { "valid" } else { "invalid" },
+            self.policy.as_deref().unwrap_or("-"),
+            self.password,
+            self.reason.as_deref().unwrap_or("")
+        )
+    }
+}
+
 fn verify_part1(min: &usize, max: &usize, target: &char, data: &str) -> bool {
     let count = data.matches(*target).count();
 
     count >= *min && count <= *max
 }
 
+/// Positions outside the password, including `0`, never hold the target.
 fn verify_part2(a: &usize, b: &usize, target: &char, data: &str) -> bool {
-    let char_a = data.chars().nth(a - 1).unwrap();
-    let char_b = data.chars().nth(b - 1).unwrap();
+    let (char_a, char_b) = chars_at(data, *a, *b);
 
-    let char_a_matches = char_a.eq(target);
-    let char_b_matches = char_b.eq(target);
+    let char_a_matches = char_a == Some(*target);
+    let char_b_matches = char_b == Some(*target);
 
     BitXor::bitxor(char_a_matches, char_b_matches)
 }
 
+/// Characters at the 1-based positions `a` and `b`, found in a single pass over `data`.
+fn chars_at(data: &str, a: usize, b: usize) -> (Option<char>, Option<char>) {
+    let (mut char_a, mut char_b) = (None, None);
+
+    for (i, c) in data.chars().enumerate().take(a.max(b)) {
+        if i + 1 == a {
+            char_a = Some(c);
+        }
+        if i + 1 == b {
+            char_b = Some(c);
+        }
+    }
+
+    (char_a, char_b)
+}
+
 fn parse_entry(raw: &str) -> Result<(usize, usize, char, &str), Box<dyn Error>> {
-    let entry: Vec<&str> = raw.split(':').collect();
+    let ((a, b, character), data) = parse_entry_with(raw, parse_policy)?;
 
-    let data = entry.get(1).ok_or("Cannot parse entry data")?.trim();
+    Ok((a, b, character, data))
+}
 
-    let params: Vec<&str> = entry
-        .get(0)
-        .ok_or("Cannot parse entry parameters")?
-        .split(' ')
-        .collect();
+/// Splits an entry with [`split_entry`] and parses its policy with `parse`. Both the puzzle
+/// solutions and [`AuditEntry::parse`] read entries through here.
+fn parse_entry_with<P, F>(raw: &str, parse: F) -> Result<(P, &str), Box<dyn Error>>
+where
+    F: FnOnce(&str) -> Result<P, Box<dyn Error>>,
+{
+    let (spec, password) = split_entry(raw)?;
 
-    let min_max: Vec<&str> = params
-        .get(0)
-        .ok_or("Cannot parse entry min and max")?
-        .split('-')
-        .collect();
+    Ok((parse(spec)?, password))
+}
 
-    let character = params
-        .get(1)
-        .ok_or("Cannot parse entry character")?
-        .chars()
-        .nth(0)
-        .unwrap();
+/// Parses the `a-b c` policy shared by [`CountPolicy`] and [`PositionPolicy`], where `c` is
+/// exactly one character.
+fn parse_policy(raw: &str) -> Result<(usize, usize, char), Box<dyn Error>> {
+    let mut params = raw.split_whitespace();
 
-    let a = min_max.get(0).ok_or("Cannot parse entry min")?.parse()?;
-    let b = min_max.get(1).ok_or("Cannot parse entry max")?.parse()?;
+    let min_max = params.next().ok_or("Cannot parse entry min and max")?;
+    let letter = params.next().ok_or("Cannot parse entry character")?;
+    if let Some(extra) = params.next() {
+        return Err(format!("Unexpected '{}' after entry character", extra).into());
+    }
 
-    Ok((a, b, character, data))
+    let mut chars = letter.chars();
+    let character = match (chars.next(), chars.next()) {
+        (Some(c), None) => c,
+        _ => {
+            return Err(format!(
+                "Entry character must be a single character, found '{}'",
+                letter
+            )
+            .into())
+        }
+    };
+
+    let (a, b) = min_max.split_once('-').ok_or("Cannot parse entry max")?;
+
+    Ok((a.parse()?, b.parse()?, character))
 }
 
 #[cfg(test)]
@@ -103,4 +474,175 @@ mod tests {
 
         assert_eq!(solution, 686);
     }
+
+    #[test]
+    fn policies() {
+        let registry = PolicyRegistry::default();
+        let verify = |name: &str, spec: &str, password: &str| {
+            registry.parse(name, spec).unwrap().verify(password)
+        };
+
+        assert!(verify("count", "1-3 a", "abcde"));
+        assert!(!verify("count", "1-3 b", "cdefg"));
+        assert!(verify("positions", "1-3 a", "abcde"));
+        assert!(!verify("positions", "2-9 c", "ccccccccc"));
+        assert!(verify("regex", "^[a-z]{5}$", "abcde"));
+        assert!(!verify("regex", "^[a-z]{5}$", "abcdef"));
+        assert!(verify("distinct", "3", "abcab"));
+        assert!(!verify("distinct", "4", "abcab"));
+        assert!(verify("forbidden", "xyz, 123", "abcde"));
+        assert!(!verify("forbidden", "xyz,cd", "abcde"));
+
+        assert!(registry.parse("count", "1-3").is_err());
+        assert!(registry.parse("regex", "(").is_err());
+        assert!(registry.parse("length", "8").is_err());
+        assert_eq!(split_entry("^a:b$: a:b").unwrap(), ("^a:b$", "a:b"));
+    }
+
+    #[test]
+    fn custom_policy() {
+        struct MinLength(usize);
+
+        impl PasswordPolicy for MinLength {
+            fn verify(&self, password: &str) -> bool {
+                password.chars().count() >= self.0
+            }
+        }
+
+        impl fmt::Display for MinLength {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "length {}", self.0)
+            }
+        }
+
+        let mut registry = PolicyRegistry::default();
+        registry.register("length", |spec| Ok(Box::new(MinLength(spec.parse()?))));
+
+        assert!(registry.names().any(|name| name == "length"));
+        assert!(registry.parse("length", "5").unwrap().verify("abcde"));
+        assert!(!registry.parse("length", "6").unwrap().verify("abcde"));
+        assert_eq!(
+            registry.parse("length", "6").unwrap().explain("abcde"),
+            Some("doesn't comply with length 6".to_string())
+        );
+    }
+
+    #[test]
+    fn audit() {
+        let registry = PolicyRegistry::default();
+        let audit = |kind: &str, raw: &str| AuditEntry::parse(1, raw, &registry, kind);
+
+        let entry = audit("count", "1-3 b: cdefg");
+        assert_eq!(entry.policy.as_deref(), Some("count 1-3 b"));
+        assert!(!entry.valid);
+        assert_eq!(entry.reason.as_deref(), Some("found 0 of 'b', need 1–3"));
+        assert_eq!(
+            entry.to_string(),
+            "1\tinvalid\tcount 1-3 b\tcdefg\tfound 0 of 'b', need 1–3"
+        );
+
+        let entry = audit("positions", "1-3 c: cbc");
+        assert_eq!(
+            entry.reason.as_deref(),
+            Some("positions 1 and 3 both match 'c'")
+        );
+        let entry = audit("positions", "1-3 c: abd");
+        assert_eq!(
+            entry.reason.as_deref(),
+            Some("neither position 1 nor 3 matches 'c'")
+        );
+
+        let entry = audit("count", "1-3 a: abcde");
+        assert!(entry.valid);
+        assert_eq!(entry.reason, None);
+        assert_eq!(entry.to_string(), "1\tvalid\tcount 1-3 a\tabcde\t");
+
+        let entry = audit("count", "1-3: abcde");
+        assert!(!entry.valid);
+        assert_eq!(entry.policy, None);
+        assert!(entry.reason.is_some());
+
+        assert_eq!(
+            audit("distinct", "4: abcab").reason.as_deref(),
+            Some("found 3 distinct characters, need 4")
+        );
+        assert_eq!(
+            audit("forbidden", "xyz,cd: abcde").reason.as_deref(),
+            Some("contains forbidden 'cd'")
+        );
+        assert_eq!(
+            audit("regex", "^b: abc").reason.as_deref(),
+            Some("doesn't match /^b/")
+        );
+    }
+
+    #[test]
+    fn audit_input() {
+        let input = fs::read_to_string("inputs/puzzle02.input").unwrap();
+        let registry = PolicyRegistry::default();
+        let valid = |kind: &str| {
+            input
+                .lines()
+                .enumerate()
+                .map(|(i, raw)| AuditEntry::parse(i + 1, raw, &registry, kind))
+                .filter(|entry| entry.valid)
+                .count() as i32
+        };
+
+        assert_eq!(valid("count"), solve_part1(input.as_str()));
+        assert_eq!(valid("positions"), solve_part2(input.as_str()));
+    }
+
+    #[test]
+    fn position_bounds() {
+        let policy = |first, second| PositionPolicy {
+            first,
+            second,
+            letter: 'é',
+        };
+
+        assert!(policy(2, 4).verify("aébc"));
+        assert!(!policy(2, 4).verify("aébé"));
+        assert!(policy(0, 2).verify("aé"));
+        assert!(policy(2, 9).verify("aé"));
+        assert!(!policy(0, 9).verify("aé"));
+        assert!(!policy(0, 0).verify(""));
+
+        assert_eq!(
+            policy(2, 4).explain("aébé").as_deref(),
+            Some("positions 2 and 4 both match 'é'")
+        );
+        assert_eq!(
+            policy(0, 9).explain("aé").as_deref(),
+            Some("neither position 0 nor 9 matches 'é', 0 and 9 outside the 2 characters of the password")
+        );
+        assert_eq!(
+            policy(1, 2).explain("bb").as_deref(),
+            Some("neither position 1 nor 2 matches 'é'")
+        );
+    }
+
+    #[test]
+    fn strict_letters() {
+        assert_eq!(parse_entry("1-3 é: aéé").unwrap(), (1, 3, 'é', "aéé"));
+        assert!(parse_entry("1-3 ab: abc").is_err());
+        assert!(parse_entry("1-3 a b: abc").is_err());
+        assert!(parse_entry("1-3 : abc").is_err());
+        assert!(parse_entry("13 a: abc").is_err());
+        assert!(parse_entry("1-3 a abc").is_err());
+    }
+
+    #[test]
+    fn entry_separator() {
+        assert_eq!(split_entry("1-3 a:abcde").unwrap(), ("1-3 a", "abcde"));
+        assert_eq!(split_entry("1-3 a : abcde ").unwrap(), ("1-3 a", "abcde"));
+        assert!(split_entry("1-3 a abcde").is_err());
+        assert_eq!(parse_entry("1-3 a:abcde").unwrap(), (1, 3, 'a', "abcde"));
+
+        let registry = PolicyRegistry::default();
+        let entry = AuditEntry::parse(1, "1-3 a:abcde", &registry, "count");
+        assert!(entry.valid);
+        assert_eq!(entry.password, "abcde");
+        assert_eq!(solve_part1("1-3 a:abcde\n1-3 b:cdefg"), 1);
+    }
 }
@@ -1,7 +1,17 @@
-use std::collections::HashMap;
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+};
+
+use serde::{ser::SerializeMap, Serialize, Serializer};
 
 use super::Puzzle;
-use regex::Regex;
+use crate::{
+    batch::read_records,
+    passport::{ColorMode, EyeColor, Field, HairColor, Height, ParseFieldError, PassportId},
+    schema::{parse_record, RecordError, Schema, Validation},
+};
 
 struct Puzzle04 {
     input: String,
@@ -13,62 +23,84 @@ impl Puzzle<(i32, i32)> for Puzzle04 {
     }
 
     fn solve(&self) -> (i32, i32) {
-        let normalized = self.input.replace(' ', "\n");
+        let passports: Vec<Passport> = read_records(self.input.as_bytes())
+            .map(|record| Passport::read(&record.unwrap().text()))
+            .collect();
 
-        let valid_passports: usize = normalized
-            .split("\n\n")
-            .map(|raw| Passport::read(raw))
+        let valid_passports: usize = passports
+            .iter()
             .filter(|passport| passport.validate_presence())
             .count();
 
-        let valid_passports_strict: usize = normalized
-            .split("\n\n")
-            .map(|raw| Passport::read(raw))
-            .filter(|passport| passport.validate_complete().iter().all(|(_, v)| *v))
+        let schema = Schema::passport();
+        let valid_passports_strict: usize = passports
+            .iter()
+            .filter(|passport| passport.validate_complete(schema).is_valid())
             .count();
 
         (valid_passports as i32, valid_passports_strict as i32)
     }
 }
 
-#[derive(Default, Debug)]
-struct Passport {
-    byr: Option<usize>,
-    iyr: Option<usize>,
-    eyr: Option<usize>,
-    hgt: Option<String>,
-    hcl: Option<String>,
-    ecl: Option<String>,
-    pid: Option<String>,
+/// Known passport fields in canonical order.
+pub const FIELDS: [&str; 8] = ["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid", "cid"];
+
+/// Passport fields as read, unknown fields are kept in `extra` and problems reading the record
+/// in `errors`.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Passport {
+    pub byr: Option<String>,
+    pub iyr: Option<String>,
+    pub eyr: Option<String>,
+    pub hgt: Option<Field<Height>>,
+    pub hcl: Option<Field<HairColor>>,
+    pub ecl: Option<Field<EyeColor>>,
+    pub pid: Option<Field<PassportId>>,
+    pub cid: Option<String>,
+    pub extra: BTreeMap<String, String>,
+    pub errors: Vec<RecordError>,
 }
 
 impl Passport {
-    fn read(input: &str) -> Self {
-        input
-            .split("\n")
-            .map(|e| {
-                let kv: Vec<&str> = e.split(":").collect();
-                (kv[0], kv[1])
-            })
-            .fold(Self::default(), |p, (k, v)| p.update(k, v))
-    }
-
-    fn update(mut self, key: &str, value: &str) -> Self {
-        match key {
-            "ecl" => self.ecl = Some(value.to_string()),
-            "pid" => self.pid = Some(value.to_string()),
-            "eyr" => self.eyr = Some(value.parse::<usize>().unwrap()),
-            "hcl" => self.hcl = Some(value.to_string()),
-            "byr" => self.byr = Some(value.parse::<usize>().unwrap()),
-            "iyr" => self.iyr = Some(value.parse::<usize>().unwrap()),
-            "hgt" => self.hgt = Some(value.to_string()),
-            _ => (),
+    pub fn read(input: &str) -> Self {
+        Self::read_with(input, ColorMode::default())
+    }
+
+    /// Reads a passport, taking hair colors as `mode` allows.
+    pub fn read_with(input: &str, mode: ColorMode) -> Self {
+        let record = parse_record(input);
+        let passport = Self {
+            errors: record.errors,
+            ..Self::default()
+        };
+
+        record
+            .fields
+            .into_iter()
+            .fold(passport, |p, (k, v)| p.update(k, v, mode))
+    }
+
+    fn update(mut self, key: String, value: String, mode: ColorMode) -> Self {
+        match key.as_str() {
+            "ecl" => self.ecl = Some(Field::read(&value)),
+            "pid" => self.pid = Some(Field::read(&value)),
+            "eyr" => self.eyr = Some(value),
+            "hcl" => {
+                self.hcl = Some(Field::read_with(&value, |s| HairColor::parse(s, mode)));
+            }
+            "byr" => self.byr = Some(value),
+            "iyr" => self.iyr = Some(value),
+            "hgt" => self.hgt = Some(Field::read(&value)),
+            "cid" => self.cid = Some(value),
+            _ => {
+                self.extra.insert(key, value);
+            }
         }
 
         self
     }
 
-    fn validate_presence(&self) -> bool {
+    pub fn validate_presence(&self) -> bool {
         let validations: Vec<bool> = vec![
             self.ecl.is_some(),
             self.pid.is_some(),
@@ -82,77 +114,145 @@ impl Passport {
         validations.into_iter().all(|i| i)
     }
 
-    fn validate_complete(&self) -> HashMap<String, bool> {
-        let hcl_re = Regex::new(r"^#(?:[0-9a-fA-F]{3}){1,2}$").unwrap();
-        let opts = &["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
-
-        let mut validations: HashMap<String, bool> = HashMap::new();
-        validations.insert("pid".into(), Self::validate_pid(&self.pid));
-        validations.insert("hgt".into(), Self::validate_hgt(&self.hgt));
-        validations.insert("hcl".into(), Self::validate_regex(&self.hcl, hcl_re));
-        validations.insert("byr".into(), Self::validate_in_range(&self.byr, 1920, 2020));
-        validations.insert("iyr".into(), Self::validate_in_range(&self.iyr, 2010, 2020));
-        validations.insert("eyr".into(), Self::validate_in_range(&self.eyr, 2020, 2030));
-        validations.insert("ecl".into(), Self::validate_in_collection(&self.ecl, opts));
-
-        validations
-    }
-
-    fn validate_hgt(hgt: &Option<String>) -> bool {
-        match hgt {
-            Some(hgt) => {
-                let len = hgt.len();
-                if len < 3 {
-                    return false;
-                }
-
-                let value = hgt[..(len - 2)].parse::<i32>();
-                if value.is_err() {
-                    return false;
-                }
-
-                let value = value.unwrap();
-                match &hgt[len - 2..] {
-                    "cm" => value >= 150 && value <= 193,
-                    "in" => value >= 59 && value <= 76,
-                    _ => false,
-                }
+    /// Checks the fields against `schema`, which alone decides what is valid. A field that
+    /// didn't parse as its type and that the schema rejects too is reported with the reason it
+    /// didn't parse.
+    pub fn validate_complete(&self, schema: &Schema) -> Validation {
+        let mut validation = schema.validate(&self.record());
+
+        for (field, error) in self.parse_errors() {
+            if let Some(invalid) = validation.invalid.iter_mut().find(|i| i.field == field) {
+                invalid.reason = error.to_string();
             }
-            None => false,
         }
+
+        validation
     }
 
-    fn validate_pid(pid: &Option<String>) -> bool {
-        match pid {
-            Some(x) => {
-                let is_numeric = x.chars().all(|c| c.is_numeric());
-                let is_nine_digits = x.len().eq(&9);
+    /// Typed fields whose value didn't parse.
+    pub fn parse_errors(&self) -> Vec<(&'static str, &ParseFieldError)> {
+        let errors = vec![
+            ("hgt", self.hgt.as_ref().and_then(Field::error)),
+            ("hcl", self.hcl.as_ref().and_then(Field::error)),
+            ("ecl", self.ecl.as_ref().and_then(Field::error)),
+            ("pid", self.pid.as_ref().and_then(Field::error)),
+        ];
 
-                is_numeric && is_nine_digits
+        errors
+            .into_iter()
+            .filter_map(|(field, error)| Some((field, error?)))
+            .collect()
+    }
+
+    /// Fields that are present, as the values a [`Schema`] checks. Typed fields are given as
+    /// they were read, so the schema's rules apply to the text rather than its canonical form.
+    pub fn record(&self) -> HashMap<String, String> {
+        let typed = vec![
+            ("hgt", self.hgt.as_ref().map(Field::raw)),
+            ("hcl", self.hcl.as_ref().map(Field::raw)),
+            ("ecl", self.ecl.as_ref().map(Field::raw)),
+            ("pid", self.pid.as_ref().map(Field::raw)),
+        ];
+        let mut record: HashMap<String, String> = self.fields().into_iter().collect();
+        for (key, raw) in typed {
+            if let Some(raw) = raw {
+                record.insert(key.to_string(), raw.to_string());
             }
-            None => false,
         }
+
+        record
     }
 
-    fn validate_in_range(item: &Option<usize>, min: usize, max: usize) -> bool {
-        match item {
-            Some(x) => *x >= min && *x <= max,
-            None => false,
-        }
+    /// Fields that are present in canonical order: the known fields as in [`FIELDS`], then any
+    /// extra field sorted by name.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let fields = vec![
+            ("byr", self.byr.clone()),
+            ("iyr", self.iyr.clone()),
+            ("eyr", self.eyr.clone()),
+            ("hgt", self.hgt.as_ref().map(Field::to_string)),
+            ("hcl", self.hcl.as_ref().map(Field::to_string)),
+            ("ecl", self.ecl.as_ref().map(Field::to_string)),
+            ("pid", self.pid.as_ref().map(Field::to_string)),
+            ("cid", self.cid.clone()),
+        ];
+
+        fields
+            .into_iter()
+            .filter_map(|(k, v)| Some((k.to_string(), v?)))
+            .chain(self.extra.clone())
+            .collect()
     }
+}
 
-    fn validate_regex(item: &Option<String>, re: Regex) -> bool {
-        match item {
-            Some(x) => re.is_match(x),
-            None => false,
-        }
+/// A normalized record: `key:value` pairs on a single line, in canonical order.
+impl fmt::Display for Passport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = self
+            .fields()
+            .into_iter()
+            .map(|(k, v)| format!("{}:{}", k, v))
+            .collect();
+
+        write!(f, "{}", fields.join(" "))
     }
+}
 
-    fn validate_in_collection(item: &Option<String>, collection: &[&str]) -> bool {
-        match item {
-            Some(x) => collection.contains(&x.as_str()),
-            None => false,
+/// Serializes the fields as an object keeping canonical order, read errors are left out.
+impl Serialize for Passport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fields = self.fields();
+        let mut map = serializer.serialize_map(Some(fields.len()))?;
+        for (k, v) in &fields {
+            map.serialize_entry(k, v)?;
         }
+
+        map.end()
+    }
+}
+
+impl Passport {
+    /// A CSV row with the value of each of `columns`, empty for the fields it doesn't have.
+    /// Values are in canonical form, as in [`Passport::fields`].
+    pub fn to_csv(&self, columns: &[String]) -> String {
+        let fields: HashMap<String, String> = self.fields().into_iter().collect();
+        let row: Vec<String> = columns
+            .iter()
+            .map(|column| csv_field(fields.get(column).map_or("", String::as_str)))
+            .collect();
+
+        row.join(",")
+    }
+}
+
+/// Columns needed to export `passports` as CSV: the known fields as in [`FIELDS`], then every
+/// extra field any of them has, sorted by name.
+pub fn csv_columns<P: Borrow<Passport>>(passports: impl IntoIterator<Item = P>) -> Vec<String> {
+    let mut extra = BTreeSet::new();
+    for passport in passports {
+        extra.extend(passport.borrow().extra.keys().cloned());
+    }
+
+    FIELDS
+        .iter()
+        .map(|field| field.to_string())
+        .chain(extra)
+        .collect()
+}
+
+/// The CSV header line for `columns`.
+pub fn csv_header(columns: &[String]) -> String {
+    let header: Vec<String> = columns.iter().map(|column| csv_field(column)).collect();
+
+    header.join(",")
+}
+
+/// Quotes a CSV value when it holds a separator, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains(&[',', '"', '\r', '\n'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
     }
 }
 
@@ -161,6 +261,7 @@ mod tests {
     use std::fs;
 
     use super::*;
+    use crate::passport::Unit;
 
     #[test]
     fn example() {
@@ -194,4 +295,187 @@ iyr:2011 ecl:brn hgt:59in"
 
         assert_eq!(solution, (264, 224))
     }
+
+    #[test]
+    fn tolerant_read() {
+        let input = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd  \r\n\
+                     byr:1937 iyr:2017 cid:147 hgt:183cm\r\n\
+                     \t\r\n\
+                     iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884 hgt:150cm\r\n\
+                     hcl:#cfa07d byr:1929 byr iyr:2014 xyz:1\r\n";
+
+        let passports: Vec<Passport> = read_records(input.as_bytes())
+            .map(|record| Passport::read(&record.unwrap().text()))
+            .collect();
+        assert_eq!(passports.len(), 2);
+        assert_eq!(passports[0].cid.as_deref(), Some("147"));
+        assert!(passports[0].errors.is_empty());
+
+        let passport = &passports[1];
+        assert_eq!(passport.iyr.as_deref(), Some("2013"));
+        assert_eq!(passport.extra.get("xyz").map(String::as_str), Some("1"));
+        assert_eq!(
+            passport.errors,
+            vec![
+                RecordError::Malformed {
+                    token: "byr".to_string()
+                },
+                RecordError::Repeated {
+                    key: "iyr".to_string(),
+                    value: "2014".to_string()
+                },
+            ]
+        );
+
+        let puzzle = Puzzle04::build(input.to_string());
+        assert_eq!(puzzle.solve(), (2, 2));
+    }
+
+    #[test]
+    fn typed_fields() {
+        let passport =
+            Passport::read("hgt:74in hcl:#1AF ecl:hzl pid:000000042 byr:1980 iyr:2012 eyr:2030");
+        assert_eq!(
+            passport.hgt.as_ref().and_then(Field::parsed),
+            Some(&Height {
+                value: 74,
+                unit: Unit::In
+            })
+        );
+        assert_eq!(
+            passport.hcl.as_ref().and_then(Field::parsed),
+            Some(&HairColor([0x11, 0xaa, 0xff]))
+        );
+        assert_eq!(
+            passport.ecl.as_ref().and_then(Field::parsed),
+            Some(&EyeColor::Hzl)
+        );
+        assert_eq!(passport.pid.as_ref().unwrap().to_string(), "000000042");
+        assert_eq!(passport.record()["hcl"], "#1AF");
+        assert_eq!(
+            passport.fields()[4],
+            ("hcl".to_string(), "#11aaff".to_string())
+        );
+
+        let schema = Schema::passport();
+        assert!(passport.validate_complete(schema).is_valid());
+
+        let passport = Passport::read("hgt:190 hcl:#123 ecl:wat pid:0123456789 byr:1980 iyr:2012");
+        assert!(!passport.validate_presence());
+        assert_eq!(passport.hgt.as_ref().unwrap().to_string(), "190");
+        assert_eq!(
+            passport.validate_complete(schema).to_string(),
+            "missing eyr; hgt '190' has no unit, expected cm or in; \
+             ecl 'wat' is not an eye color; pid '0123456789' doesn't match ^[0-9]{9}$"
+        );
+    }
+
+    #[test]
+    fn normalize() {
+        let passport = Passport::read(
+            "zzz:1 pid:000000042 hcl:#1AF\nhgt:074in cid:7 ecl:hzl aaa:x,\"y\" byr:1980 iyr:2012",
+        );
+
+        assert_eq!(
+            passport.to_string(),
+            "byr:1980 iyr:2012 hgt:74in hcl:#11aaff ecl:hzl pid:000000042 cid:7 aaa:x,\"y\" zzz:1"
+        );
+        assert_eq!(
+            Passport::read(&passport.to_string()).to_string(),
+            passport.to_string()
+        );
+        assert_eq!(passport.record()["hgt"], "074in");
+        assert_eq!(
+            serde_json::to_string(&passport).unwrap(),
+            r##"{"byr":"1980","iyr":"2012","hgt":"74in","hcl":"#11aaff","ecl":"hzl","pid":"000000042","cid":"7","aaa":"x,\"y\"","zzz":"1"}"##
+        );
+        assert_eq!(Passport::read("").to_string(), "");
+    }
+
+    #[test]
+    fn csv_export() {
+        let passports = vec![
+            Passport::read("pid:000000042 hcl:#1AF zzz:1 aaa:x,\"y\""),
+            Passport::read("byr:1980 hgt:074in"),
+            Passport::read("ecl:hzl bbb:a\"b"),
+        ];
+        let columns = csv_columns(&passports);
+
+        assert_eq!(
+            csv_header(&columns),
+            "byr,iyr,eyr,hgt,hcl,ecl,pid,cid,aaa,bbb,zzz"
+        );
+        let rows: Vec<String> = passports.iter().map(|p| p.to_csv(&columns)).collect();
+        assert_eq!(
+            rows,
+            vec![
+                ",,,,#11aaff,,000000042,,\"x,\"\"y\"\"\",,1",
+                "1980,,,74in,,,,,,,",
+                ",,,,,hzl,,,,\"a\"\"b\",",
+            ]
+        );
+
+        assert_eq!(csv_columns(Vec::<Passport>::new()), FIELDS.to_vec());
+        assert_eq!(Passport::read("").to_csv(&columns), ",".repeat(10));
+        assert_eq!(
+            csv_header(&["a,b".to_string(), "c".to_string()]),
+            "\"a,b\",c"
+        );
+    }
+
+    #[test]
+    fn custom_schema() {
+        let schema = Schema::from_json(
+            r#"{ "name": "size", "fields": [{ "name": "hgt", "type": "string" }] }"#,
+        )
+        .unwrap();
+        let valid = |raw| Passport::read(raw).validate_complete(&schema).is_valid();
+
+        assert!(valid("hgt:tall"));
+        assert!(valid("hgt:180cm"));
+        assert!(valid("hgt:180cm ecl:wat"));
+        assert!(!valid("ecl:brn"));
+        for raw in &["hgt:tall", "hgt:180cm", "ecl:brn"] {
+            assert_eq!(
+                Passport::read(raw).validate_complete(&schema),
+                schema.validate(&parse_record(raw).fields)
+            );
+        }
+    }
+
+    #[test]
+    fn strict_hair_color() {
+        let raw = "byr:1980 iyr:2012 eyr:2030 hgt:74in hcl:#1af ecl:hzl pid:000000042";
+
+        // The strict rules apply to the text as written, however the passport was read.
+        let passport = Passport::read(raw);
+        assert!(passport.validate_complete(Schema::passport()).is_valid());
+        assert_eq!(
+            passport
+                .validate_complete(Schema::passport_strict())
+                .to_string(),
+            "hcl '#1af' doesn't match ^#[0-9a-f]{6}$"
+        );
+        let upper = Passport::read(&raw.replace("#1af", "#11AAFF"));
+        assert!(!upper
+            .validate_complete(Schema::passport_strict())
+            .is_valid());
+
+        // Read strictly, the shorthand doesn't parse but the schema still decides.
+        let passport = Passport::read_with(raw, ColorMode::Strict);
+        assert_eq!(passport.to_string(), raw);
+        assert!(passport.validate_complete(Schema::passport()).is_valid());
+        assert_eq!(
+            passport
+                .validate_complete(Schema::passport_strict())
+                .to_string(),
+            "hcl '#1af' is not a lowercase #rrggbb color"
+        );
+        assert_eq!(
+            Schema::passport_strict()
+                .validate(&parse_record(raw).fields)
+                .to_string(),
+            "hcl '#1af' doesn't match ^#[0-9a-f]{6}$"
+        );
+    }
 }
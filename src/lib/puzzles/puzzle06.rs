@@ -1,18 +1,35 @@
-use itertools::Itertools;
+use std::collections::HashSet;
 
-use crate::{decode_lines_group, frequencies};
+use crate::batch::{read_records, FieldSeparator, RawRecord};
+
+/// Blank line separated groups, with the answers of a person on each line.
+fn groups<'a, I: Into<&'a str>>(input: I) -> impl Iterator<Item = RawRecord> + 'a {
+    read_records(input.into().as_bytes())
+        .fields_separated_by(FieldSeparator::Line)
+        .map(|group| group.unwrap())
+}
+
+fn answers(person: &str) -> HashSet<char> {
+    person.trim().chars().collect()
+}
 
 fn solve_part1<'a, I: Into<&'a str>>(input: I) -> i32 {
-    decode_lines_group(input, |s| s.chars().filter(|&c| c != '\n').unique().count()).sum::<usize>()
-        as i32
+    groups(input)
+        .map(|group| {
+            let people = group.fields().into_iter().map(answers);
+            people
+                .fold(HashSet::new(), |any, person| &any | &person)
+                .len()
+        })
+        .sum::<usize>() as i32
 }
 
 fn solve_part2<'a, I: Into<&'a str>>(input: I) -> i32 {
-    decode_lines_group(input, |group| (group, group.split("\n").count()))
-        .map(|(group, group_size)| {
-            frequencies(group)
-                .filter(|(c, count)| *c != '\n' && *count == group_size)
-                .count()
+    groups(input)
+        .map(|group| {
+            let mut people = group.fields().into_iter().map(answers);
+            let first = people.next().unwrap_or_default();
+            people.fold(first, |all, person| &all & &person).len()
         })
         .sum::<usize>() as i32
 }
@@ -85,4 +102,12 @@ b";
 
         assert_eq!(solution, 3435)
     }
+
+    #[test]
+    fn crlf_input() {
+        let input = "abc\r\n\r\na\r\nb\r\nc\r\n\r\nab\r\nac\r\n \r\na\r\na\r\na\r\na\r\n\r\nb\r\n";
+
+        assert_eq!(solve_part1(input), 11);
+        assert_eq!(solve_part2(input), 6);
+    }
 }
@@ -1,137 +1,482 @@
-use itertools::Itertools;
-use regex::Regex;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
     io::{BufRead, Lines},
 };
 
-use crate::concat_vec;
+use crate::{
+    graph::{Direction, Graph, PathSumError},
+    OverflowError,
+};
 
-#[derive(Debug, Clone)]
-struct RVec<T>(T, Vec<RVec<T>>);
+/// A single `X bags contain N Y bag(s), ...` rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub color: String,
+    pub contents: Vec<(String, usize)>,
+}
 
-fn solve_part1<T: BufRead>(input: Lines<T>) -> i32 {
-    let parent_re = Regex::new(r"(.+)\sbags\scontain\s(.*)\.").unwrap();
-    let content_re = Regex::new(r"(\d+)\s(.+?)\sbags?").unwrap();
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleErrorKind {
+    Expected(&'static str),
+    ExpectedWord,
+    ExpectedQuantity,
+    EmptyColor,
+    InvalidQuantity(String),
+    QuantityMismatch(usize, &'static str),
+    TrailingInput(String),
+}
 
-    let db = input.fold(HashMap::new(), |db, line| {
-        build_db_rev(db, &parent_re, &content_re, &line.unwrap())
-    });
-    let parents = trace_path(&db, ("shiny gold", 0));
-    let uniques = flatten_db(&parents)
-        .iter()
-        .map(|(color, _)| color)
-        .unique()
-        .count();
-    let result = uniques - 1;
+/// Error returned by [`parse_rule`], `column` is the byte offset where parsing failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleError {
+    pub column: usize,
+    pub kind: RuleErrorKind,
+}
 
-    result as i32
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            RuleErrorKind::Expected(token) => {
+                write!(f, "column {}: expected '{}'", self.column, token)
+            }
+            RuleErrorKind::ExpectedWord => write!(f, "column {}: expected a word", self.column),
+            RuleErrorKind::ExpectedQuantity => {
+                write!(f, "column {}: expected a quantity", self.column)
+            }
+            RuleErrorKind::EmptyColor => write!(f, "column {}: missing bag color", self.column),
+            RuleErrorKind::InvalidQuantity(q) => {
+                write!(f, "column {}: invalid quantity '{}'", self.column, q)
+            }
+            RuleErrorKind::QuantityMismatch(n, word) => {
+                write!(
+                    f,
+                    "column {}: quantity {} does not match '{}'",
+                    self.column, n, word
+                )
+            }
+            RuleErrorKind::TrailingInput(rest) => {
+                write!(
+                    f,
+                    "column {}: unexpected trailing input '{}'",
+                    self.column, rest
+                )
+            }
+        }
+    }
 }
 
-fn solve_part2<T: BufRead>(input: Lines<T>) -> i32 {
-    let parent_re = Regex::new(r"(.+)\sbags\scontain\s(.*)\.").unwrap();
-    let content_re = Regex::new(r"(\d+)\s(.+?)\sbags?").unwrap();
+impl Error for RuleError {}
+
+struct Cursor<'a> {
+    input: &'a str,
+    pos: usize,
+}
 
-    let db = input.fold(HashMap::new(), |db, line| {
-        build_db(db, &parent_re, &content_re, &line.unwrap())
-    });
+impl<'a> Cursor<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
 
-    let parents = trace_path(&db, ("shiny gold", 1));
-    let total = compute_total(&parents) - 1;
+    fn error(&self, kind: RuleErrorKind) -> RuleError {
+        RuleError {
+            column: self.pos,
+            kind,
+        }
+    }
 
-    total as i32
-}
+    fn eat(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
 
-fn trace_path<'a>(
-    db: &'a HashMap<String, Vec<(String, usize)>>,
-    item: (&'a str, usize),
-) -> RVec<(&'a str, usize)> {
-    match db.get(item.0) {
-        None => RVec(item, Vec::new()),
-        Some(xs) => RVec(
-            item,
-            xs.iter()
-                .map(|(item, amount)| trace_path(db, (&item, *amount)))
-                .collect(),
-        ),
+    fn expect(&mut self, token: &'static str) -> Result<(), RuleError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(RuleErrorKind::Expected(token)))
+        }
     }
-}
 
-fn flatten_db<'a>(ps: &'a RVec<(&str, usize)>) -> Vec<(&'a str, usize)> {
-    match &ps.1[..] {
-        [] => vec![ps.0],
-        _ => {
-            ps.1.iter()
-                .fold(vec![ps.0], |res, ps| {
-                    concat_vec(
-                        flatten_db(ps),
-                        res.iter()
-                            .map(|(color, amount)| (*color, amount * ps.0 .1))
-                            .collect(),
-                    )
-                    .map(|(a, b)| (a, b))
-                    .collect()
-                })
-                .into_iter()
-                .map(|(a, b)| (a, b))
-                .collect()
+    fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Reads the words of a color up to (but not including) the `bag`/`bags` keyword.
+    fn color(&mut self) -> Result<&'a str, RuleError> {
+        let start = self.pos;
+
+        loop {
+            let word_start = self.pos;
+            let word = self.take_while(|c| c.is_alphabetic());
+
+            match word {
+                "" => return Err(self.error(RuleErrorKind::ExpectedWord)),
+                "bag" | "bags" if word_start == start => {
+                    self.pos = word_start;
+                    return Err(self.error(RuleErrorKind::EmptyColor));
+                }
+                "bag" | "bags" => {
+                    self.pos = word_start;
+                    return Ok(&self.input[start..word_start - 1]);
+                }
+                _ => self.expect(" ")?,
+            }
+        }
+    }
+
+    fn bag_keyword(&mut self) -> Result<&'static str, RuleError> {
+        if self.eat("bags") {
+            Ok("bags")
+        } else {
+            self.expect("bag").map(|_| "bag")
+        }
+    }
+
+    fn item(&mut self) -> Result<(String, usize), RuleError> {
+        let start = self.pos;
+        let raw = self.take_while(|c| c.is_ascii_digit());
+        if raw.is_empty() {
+            return Err(self.error(RuleErrorKind::ExpectedQuantity));
+        }
+
+        let amount = match raw.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => {
+                self.pos = start;
+                return Err(self.error(RuleErrorKind::InvalidQuantity(raw.to_string())));
+            }
+        };
+
+        self.expect(" ")?;
+        let color = self.color()?;
+
+        let keyword_start = self.pos;
+        let keyword = self.bag_keyword()?;
+        if (amount == 1) != (keyword == "bag") {
+            self.pos = keyword_start;
+            return Err(self.error(RuleErrorKind::QuantityMismatch(amount, keyword)));
         }
+
+        Ok((color.to_string(), amount))
     }
 }
 
-fn build_db(
-    db: HashMap<String, Vec<(String, usize)>>,
-    parent_re: &Regex,
-    content_re: &Regex,
-    line: &str,
-) -> HashMap<String, Vec<(String, usize)>> {
-    let mut db = db.clone();
+/// Parses a rule following the grammar:
+///
+/// ```text
+/// rule     := color " bags contain " contents "."
+/// contents := "no other bags" | item (", " item)*
+/// item     := quantity " " color (" bag" | " bags")
+/// ```
+pub fn parse_rule(line: &str) -> Result<Rule, RuleError> {
+    let mut cursor = Cursor {
+        input: line,
+        pos: 0,
+    };
 
-    for cap in parent_re.captures_iter(line) {
-        let parent_color = cap[1].to_string();
+    let color = cursor.color()?.to_string();
+    cursor.expect("bags contain ")?;
 
-        for bag in content_re.captures_iter(line) {
-            let amount = bag[1].parse::<usize>().unwrap();
-            let color = bag[2].to_string();
-            let val = db.entry(parent_color.clone()).or_insert(Vec::new());
+    let mut contents = Vec::new();
+    if !cursor.eat("no other bags") {
+        loop {
+            contents.push(cursor.item()?);
 
-            val.push((color, amount));
+            if !cursor.eat(", ") {
+                break;
+            }
         }
     }
 
-    db
+    cursor.expect(".")?;
+    if !cursor.rest().is_empty() {
+        return Err(cursor.error(RuleErrorKind::TrailingInput(cursor.rest().to_string())));
+    }
+
+    Ok(Rule { color, contents })
+}
+
+fn solve_part1<T: BufRead>(input: Lines<T>) -> i32 {
+    let mut rules = RuleSet::from_lines(input).unwrap();
+
+    rules.containers("shiny gold") as i32
 }
 
-fn build_db_rev(
-    db: HashMap<String, Vec<(String, usize)>>,
-    parent_re: &Regex,
-    content_re: &Regex,
-    line: &str,
-) -> HashMap<String, Vec<(String, usize)>> {
-    let mut db = db.clone();
+fn solve_part2<T: BufRead>(input: Lines<T>) -> i32 {
+    let mut rules = RuleSet::from_lines(input).unwrap();
+
+    rules.contents("shiny gold").unwrap() as i32
+}
+
+/// Incrementally maintained set of rules. Adding or removing a rule only invalidates the cached
+/// totals of the affected color and the colors containing it, and the cached container counts
+/// of the colors inside it.
+#[derive(Debug, Default)]
+pub struct RuleSet {
+    graph: Graph<String, usize>,
+    rules: HashSet<usize>,
+    totals: HashMap<usize, usize>,
+    containers: HashMap<usize, usize>,
+}
+
+impl RuleSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_lines<T: BufRead>(input: Lines<T>) -> Result<Self, Box<dyn Error>> {
+        let mut rules = Self::new();
+
+        for line in input {
+            rules.push_line(&line?)?;
+        }
+
+        Ok(rules)
+    }
+
+    pub fn push_line(&mut self, line: &str) -> Result<Option<Rule>, RuleError> {
+        Ok(self.add_rule(parse_rule(line)?))
+    }
+
+    /// Forward graph (bag color -> contained colors, weighted by amount).
+    pub fn graph(&self) -> &Graph<String, usize> {
+        &self.graph
+    }
+
+    /// Adds a rule, returning the previous rule for the same color if it was replaced. A color
+    /// listed more than once holds the sum of its amounts.
+    pub fn add_rule(&mut self, rule: Rule) -> Option<Rule> {
+        let previous = self.remove_rule(&rule.color);
+        let parent = self.graph.add_node(rule.color);
+
+        let mut contents: Vec<(usize, usize)> = Vec::with_capacity(rule.contents.len());
+        for (color, amount) in rule.contents {
+            let child = self.graph.add_node(color);
+            match contents.iter_mut().find(|(id, _)| *id == child) {
+                Some((_, total)) => *total += amount,
+                None => contents.push((child, amount)),
+            }
+        }
+        for (child, amount) in contents {
+            self.graph.add_edge(parent, child, amount);
+        }
+        self.rules.insert(parent);
+        self.invalidate(parent);
+        self.invalidate_containers(parent);
+
+        previous
+    }
+
+    pub fn remove_rule(&mut self, color: &str) -> Option<Rule> {
+        let id = self.graph.node(color)?;
+        if !self.rules.remove(&id) {
+            return None;
+        }
+
+        self.invalidate_containers(id);
+        let contents = self
+            .graph
+            .remove_edges(id)
+            .into_iter()
+            .map(|(child, amount)| (self.graph.label(child).clone(), amount))
+            .collect();
+        self.invalidate(id);
+
+        Some(Rule {
+            color: color.to_string(),
+            contents,
+        })
+    }
+
+    /// Number of distinct colors that eventually contain `color`.
+    pub fn containers(&mut self, color: &str) -> usize {
+        let id = match self.graph.node(color) {
+            Some(id) => id,
+            None => return 0,
+        };
+        let graph = &self.graph;
+
+        *self
+            .containers
+            .entry(id)
+            .or_insert_with(|| graph.reachable(id, Direction::Reverse).len())
+    }
 
-    for cap in parent_re.captures_iter(line) {
-        let parent_color = cap[1].to_string();
+    /// Number of bags inside a `color` bag. Fails if the rules reachable from it are cyclic or
+    /// if the number doesn't fit in a `usize`.
+    pub fn contents(&mut self, color: &str) -> Result<usize, PathSumError> {
+        match self.graph.node(color) {
+            Some(id) => self.graph.path_product_sum(id, &mut self.totals),
+            None => Ok(0),
+        }
+    }
+
+    /// Drops the cached totals of `id` and every color containing it. A color is only cached
+    /// after all of its contents are, so the walk stops at the first uncached color.
+    fn invalidate(&mut self, id: usize) {
+        let mut stack = vec![id];
 
-        for bag in content_re.captures_iter(line) {
-            let amount = bag[1].parse::<usize>().unwrap();
-            let color = bag[2].to_string();
-            let val = db.entry(color).or_insert(Vec::new());
+        while let Some(current) = stack.pop() {
+            if self.totals.remove(&current).is_some() {
+                let parents = self.graph.edges(current, Direction::Reverse);
+                stack.extend(parents.iter().map(|(parent, _)| *parent));
+            }
+        }
+    }
 
-            val.push((parent_color.clone(), amount));
+    /// Drops the cached container counts of every color inside `id`, the only ones an edge
+    /// leaving `id` can change. Has to run while those edges are still in the graph.
+    fn invalidate_containers(&mut self, id: usize) {
+        if self.containers.is_empty() {
+            return;
         }
+
+        for inside in self.graph.dfs(id, Direction::Forward) {
+            self.containers.remove(&inside);
+        }
+    }
+}
+
+/// A chain of bags starting at `from`, each hop holds `amount` bags of the next color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BagPath {
+    pub from: String,
+    pub hops: Vec<(String, usize)>,
+}
+
+impl BagPath {
+    /// The chain through `ids`, `None` if it is empty or two consecutive colors aren't joined
+    /// by a rule.
+    fn new(graph: &Graph<String, usize>, ids: &[usize]) -> Option<Self> {
+        let hops = ids
+            .windows(2)
+            .map(|w| Some((graph.label(w[1]).clone(), graph.weight(w[0], w[1])?)))
+            .collect::<Option<_>>()?;
+
+        Some(Self {
+            from: graph.label(*ids.first()?).clone(),
+            hops,
+        })
+    }
+
+    /// Number of bags of the last color reached through this chain, `None` if it overflows.
+    pub fn total(&self) -> Option<usize> {
+        self.hops
+            .iter()
+            .try_fold(1usize, |total, (_, amount)| total.checked_mul(*amount))
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathObjective {
+    Fewest,
+    Most,
+}
+
+/// Returns the path from `from` to `to` with the fewest hops.
+pub fn path(graph: &Graph<String, usize>, from: &str, to: &str) -> Option<BagPath> {
+    let ids = graph.shortest_path(graph.node(from)?, graph.node(to)?)?;
 
-    db
+    BagPath::new(graph, &ids)
 }
 
-fn compute_total(db: &RVec<(&str, usize)>) -> usize {
-    let RVec((_, amount), content) = db;
+/// Enumerates every simple path from `from` to `to`.
+pub fn all_paths(graph: &Graph<String, usize>, from: &str, to: &str) -> Vec<BagPath> {
+    match (graph.node(from), graph.node(to)) {
+        (Some(from), Some(to)) => graph
+            .simple_paths(from, to)
+            .iter()
+            .filter_map(|ids| BagPath::new(graph, ids))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the path from `from` to `to` holding the fewest or most bags of `to`, or an error if
+/// the number of bags along some path overflows `usize`. Rules are expected to be acyclic, bags
+/// on a cycle are treated as unreachable.
+pub fn optimal_path(
+    graph: &Graph<String, usize>,
+    from: &str,
+    to: &str,
+    objective: PathObjective,
+) -> Result<Option<BagPath>, OverflowError> {
+    let (from, to) = match (graph.node(from), graph.node(to)) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return Ok(None),
+    };
+    let best = best_totals(graph, from, to, objective)?;
+
+    let mut ids = vec![from];
+    while let Some((_, Some(next))) = best.get(ids.last().unwrap()) {
+        ids.push(*next);
+    }
+    if ids.last() != Some(&to) {
+        return Ok(None);
+    }
+
+    Ok(BagPath::new(graph, &ids))
+}
 
-    match &content[..] {
-        [] => *amount,
-        _ => content.iter().map(|x| compute_total(x)).sum::<usize>() * amount + amount,
+/// Best number of bags of `to` inside each bag reachable from `from` that leads to `to`, along
+/// with the next bag on the way. Bags are visited with an explicit stack, in post-order, so long
+/// chains of rules can't overflow the call stack. An edge back to a bag still being visited is
+/// on a cycle and skipped.
+fn best_totals(
+    graph: &Graph<String, usize>,
+    from: usize,
+    to: usize,
+    objective: PathObjective,
+) -> Result<HashMap<usize, (usize, Option<usize>)>, OverflowError> {
+    let mut best: HashMap<usize, (usize, Option<usize>)> = HashMap::new();
+    let mut done: HashSet<usize> = HashSet::new();
+    let mut in_progress: HashSet<usize> = HashSet::new();
+    let mut stack = vec![(from, false)];
+
+    while let Some((id, expanded)) = stack.pop() {
+        if expanded {
+            let mut found: Option<(usize, Option<usize>)> = None;
+            for (next, amount) in graph.edges(id, Direction::Forward) {
+                if let Some((total, _)) = best.get(next) {
+                    let total = total.checked_mul(*amount).ok_or(OverflowError)?;
+                    let better = match (found, objective) {
+                        (None, _) => true,
+                        (Some((b, _)), PathObjective::Fewest) => total < b,
+                        (Some((b, _)), PathObjective::Most) => total > b,
+                    };
+
+                    if better {
+                        found = Some((total, Some(*next)));
+                    }
+                }
+            }
+
+            if let Some(found) = found {
+                best.insert(id, found);
+            }
+            in_progress.remove(&id);
+            done.insert(id);
+        } else if id == to {
+            best.insert(id, (1, None));
+            done.insert(id);
+        } else if !done.contains(&id) && in_progress.insert(id) {
+            stack.push((id, true));
+
+            let edges = graph.edges(id, Direction::Forward);
+            stack.extend(edges.iter().rev().map(|(next, _)| (*next, false)));
+        }
     }
+
+    Ok(best)
 }
 
 #[cfg(test)]
@@ -199,4 +544,284 @@ dotted black bags contain no other bags.";
 
         assert_eq!(solution, 34988)
     }
+
+    #[test]
+    fn parse_rule_valid() {
+        let rule = parse_rule("light red bags contain 1 bright white bag, 2 muted yellow bags.");
+
+        assert_eq!(
+            rule,
+            Ok(Rule {
+                color: "light red".to_string(),
+                contents: vec![
+                    ("bright white".to_string(), 1),
+                    ("muted yellow".to_string(), 2)
+                ],
+            })
+        );
+
+        let rule = parse_rule("faded blue bags contain no other bags.");
+
+        assert_eq!(
+            rule,
+            Ok(Rule {
+                color: "faded blue".to_string(),
+                contents: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn parse_rule_malformed() {
+        let err = |line| parse_rule(line).unwrap_err();
+
+        assert_eq!(
+            err("faded blue bags contain no other bags"),
+            RuleError {
+                column: 37,
+                kind: RuleErrorKind::Expected("."),
+            }
+        );
+        assert_eq!(
+            err("bags contain no other bags.").kind,
+            RuleErrorKind::EmptyColor
+        );
+        assert_eq!(
+            err("light red bags hold 1 bright white bag.").kind,
+            RuleErrorKind::Expected("bags contain ")
+        );
+        assert_eq!(
+            err("light red bags contain 0 bright white bags.").kind,
+            RuleErrorKind::InvalidQuantity("0".to_string())
+        );
+        assert_eq!(
+            err("light red bags contain 2 bright white bag.").kind,
+            RuleErrorKind::QuantityMismatch(2, "bag")
+        );
+        assert_eq!(
+            err("light red bags contain 1 bright white bag, muted yellow bags.").kind,
+            RuleErrorKind::ExpectedQuantity
+        );
+        assert_eq!(
+            err("light red bags contain no other bags. extra").kind,
+            RuleErrorKind::TrailingInput(" extra".to_string())
+        );
+    }
+
+    #[test]
+    fn paths_example() {
+        let content = "\
+light red bags contain 1 bright white bag, 2 muted yellow bags.
+dark orange bags contain 3 bright white bags, 4 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+dark olive bags contain 3 faded blue bags, 4 dotted black bags.
+vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
+faded blue bags contain no other bags.
+dotted black bags contain no other bags.";
+        let rules = RuleSet::from_lines(Cursor::new(content).lines()).unwrap();
+        let db = rules.graph();
+
+        let shortest = path(db, "light red", "faded blue").unwrap();
+        assert_eq!(shortest.from, "light red");
+        assert_eq!(
+            shortest.hops,
+            vec![
+                ("muted yellow".to_string(), 2),
+                ("faded blue".to_string(), 9)
+            ]
+        );
+        assert_eq!(shortest.total(), Some(18));
+
+        assert_eq!(path(db, "faded blue", "shiny gold"), None);
+        assert_eq!(all_paths(db, "shiny gold", "faded blue").len(), 2);
+        assert_eq!(all_paths(db, "light red", "dotted black").len(), 4);
+
+        let id = |color| db.node(color).unwrap();
+        assert_eq!(BagPath::new(db, &[id("light red"), id("shiny gold")]), None);
+        assert_eq!(BagPath::new(db, &[]), None);
+        assert_eq!(
+            BagPath::new(db, &[id("faded blue")]).unwrap().total(),
+            Some(1)
+        );
+
+        let fewest = optimal_path(db, "shiny gold", "dotted black", PathObjective::Fewest);
+        assert_eq!(fewest.unwrap().unwrap().total(), Some(4));
+
+        let most = optimal_path(db, "light red", "faded blue", PathObjective::Most)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            most.hops,
+            vec![
+                ("muted yellow".to_string(), 2),
+                ("shiny gold".to_string(), 2),
+                ("vibrant plum".to_string(), 2),
+                ("faded blue".to_string(), 5)
+            ]
+        );
+        assert_eq!(most.total(), Some(40));
+        assert_eq!(
+            optimal_path(db, "faded blue", "shiny gold", PathObjective::Most),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn path_overflow() {
+        // Every level holds 4096 bags of the next one, six levels overflow a 64-bit usize.
+        let content = "\
+level a bags contain 4096 level b bags.
+level b bags contain 4096 level c bags.
+level c bags contain 4096 level d bags.
+level d bags contain 4096 level e bags.
+level e bags contain 4096 level f bags.
+level f bags contain 4096 level g bags.
+level g bags contain no other bags.";
+        let rules = RuleSet::from_lines(Cursor::new(content).lines()).unwrap();
+        let db = rules.graph();
+
+        let most = |from| optimal_path(db, from, "level g", PathObjective::Most);
+        assert_eq!(most("level b").unwrap().unwrap().total(), Some(1 << 60));
+        assert_eq!(most("level a"), Err(OverflowError));
+        assert_eq!(path(db, "level a", "level g").unwrap().total(), None);
+    }
+
+    #[test]
+    fn long_chain_path() {
+        // Colors are words, so the level is written in base 26 with letters. The `x` keeps
+        // levels such as `bag` from reading as the keyword.
+        let color = |mut level: usize| {
+            let mut word = String::new();
+            loop {
+                word.insert(0, (b'a' + (level % 26) as u8) as char);
+                level /= 26;
+                if level == 0 {
+                    break format!("level x{}", word);
+                }
+            }
+        };
+
+        let mut rules = RuleSet::new();
+        for level in 0..100_000 {
+            let rule = format!("{} bags contain 1 {} bag.", color(level), color(level + 1));
+            rules.push_line(&rule).unwrap();
+        }
+        let (top, bottom) = (color(0), color(100_000));
+
+        let db = rules.graph();
+        let fewest = optimal_path(db, &top, &bottom, PathObjective::Fewest);
+        let fewest = fewest.unwrap().unwrap();
+        assert_eq!(fewest.hops.len(), 100_000);
+        assert_eq!(fewest.total(), Some(1));
+        assert_eq!(
+            optimal_path(db, &bottom, &top, PathObjective::Most),
+            Ok(None)
+        );
+        assert_eq!(rules.contents(&top), Ok(100_000));
+    }
+
+    #[test]
+    fn cyclic_paths() {
+        let content = "\
+light red bags contain 2 bright white bags, 3 muted yellow bags.
+bright white bags contain 1 light red bag, 4 shiny gold bags.
+muted yellow bags contain 1 shiny gold bag.
+shiny gold bags contain no other bags.";
+        let rules = RuleSet::from_lines(Cursor::new(content).lines()).unwrap();
+        let db = rules.graph();
+
+        let most = optimal_path(db, "light red", "shiny gold", PathObjective::Most);
+        assert_eq!(most.unwrap().unwrap().total(), Some(8));
+        let fewest = optimal_path(db, "bright white", "shiny gold", PathObjective::Fewest);
+        assert_eq!(fewest.unwrap().unwrap().total(), Some(3));
+    }
+
+    #[test]
+    fn rule_set_updates() {
+        let content = "\
+shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+dark olive bags contain 3 faded blue bags, 4 dotted black bags.
+vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
+faded blue bags contain no other bags.
+dotted black bags contain no other bags.";
+        let mut rules = RuleSet::from_lines(Cursor::new(content).lines()).unwrap();
+
+        assert_eq!(rules.contents("shiny gold"), Ok(32));
+        assert_eq!(rules.containers("faded blue"), 3);
+
+        rules
+            .push_line("faded blue bags contain 2 dotted black bags.")
+            .unwrap();
+        assert_eq!(rules.contents("shiny gold"), Ok(32 + 13 * 2));
+
+        let removed = rules.remove_rule("vibrant plum").unwrap();
+        assert_eq!(removed.contents.len(), 2);
+        assert_eq!(rules.contents("shiny gold"), Ok(1 + 3 * 3 + 4 + 2));
+        assert_eq!(rules.containers("faded blue"), 2);
+
+        rules
+            .push_line("dotted black bags contain 1 shiny gold bag.")
+            .unwrap();
+        let gold = rules.graph().node("shiny gold").unwrap();
+        assert_eq!(rules.contents("shiny gold"), Err(PathSumError::Cycle(gold)));
+    }
+
+    #[test]
+    fn rule_set_containers() {
+        let content = "\
+light red bags contain 1 bright white bag, 2 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain 1 dark olive bag.
+dark olive bags contain no other bags.";
+        let mut rules = RuleSet::from_lines(Cursor::new(content).lines()).unwrap();
+
+        assert_eq!(rules.containers("shiny gold"), 3);
+        assert_eq!(rules.containers("dark olive"), 4);
+        assert_eq!(rules.containers("faded blue"), 2);
+
+        rules
+            .push_line("dotted black bags contain 1 light red bag.")
+            .unwrap();
+        assert_eq!(rules.containers("shiny gold"), 4);
+        assert_eq!(rules.containers("dark olive"), 5);
+
+        rules.remove_rule("muted yellow").unwrap();
+        assert_eq!(rules.containers("shiny gold"), 3);
+        assert_eq!(rules.containers("faded blue"), 0);
+        assert_eq!(rules.containers("light red"), 1);
+
+        rules
+            .push_line("faded blue bags contain 3 shiny gold bags.")
+            .unwrap();
+        assert_eq!(rules.containers("shiny gold"), 4);
+        assert_eq!(rules.containers("faded blue"), 0);
+        assert_eq!(rules.containers("missing"), 0);
+    }
+
+    #[test]
+    fn repeated_contents() {
+        let content = "\
+shiny gold bags contain 2 dark red bags, 1 dotted black bag, 3 dark red bags.
+dark red bags contain 2 dotted black bags.
+dotted black bags contain no other bags.";
+        let mut rules = RuleSet::from_lines(Cursor::new(content).lines()).unwrap();
+
+        let gold = rules.graph().node("shiny gold").unwrap();
+        let red = rules.graph().node("dark red").unwrap();
+        assert_eq!(rules.graph().edges(gold, Direction::Forward).len(), 2);
+        assert_eq!(rules.graph().weight(gold, red), Some(5));
+        assert_eq!(rules.contents("shiny gold"), Ok(5 + 5 * 2 + 1));
+        assert_eq!(rules.containers("dark red"), 1);
+        assert_eq!(rules.containers("dotted black"), 2);
+
+        let removed = rules.remove_rule("shiny gold").unwrap();
+        assert_eq!(
+            removed.contents,
+            vec![("dark red".to_string(), 5), ("dotted black".to_string(), 1)]
+        );
+        assert_eq!(rules.containers("dotted black"), 1);
+    }
 }
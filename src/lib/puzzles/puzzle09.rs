@@ -1,103 +1,264 @@
-use itertools::Itertools;
-use std::io::{BufRead, Lines};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "parallel")]
+use std::collections::HashSet;
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt,
+    io::{BufRead, Lines},
+    ops::Range,
+};
 
-fn solve_part1<T: BufRead>(input: Lines<T>, window_size: usize) -> i64 {
-    let solution = input
-        .map(|x| x.unwrap().parse::<i64>().unwrap())
-        .collect::<Vec<i64>>()
-        .windows(window_size + 1)
-        .map(|xs| {
-            (
-                xs.last().unwrap(),
-                xs.iter().take(window_size).collect::<Vec<&i64>>(),
-            )
-        })
-        .filter(|(n, window)| {
-            window
-                .iter()
-                .enumerate()
-                .cartesian_product(window.iter().enumerate())
-                .take(window.len().pow(2))
-                .filter_map(
-                    |((i1, n1), (i2, n2))| {
-                        if i1 != i2 {
-                            Some(*n1 + *n2)
-                        } else {
-                            None
-                        }
-                    },
-                )
-                .all(|sum| sum != **n)
-        })
-        .map(|(n, _)| *n)
-        .take(1)
-        .collect::<Vec<i64>>();
+use serde::Serialize;
+
+use crate::seq::{self, checked_sum, Window};
 
-    *solution.first().expect("Solution not found")
+fn solve_part1<T: BufRead>(input: Lines<T>, window_size: usize) -> Result<i64, Box<dyn Error>> {
+    let data = input.map(|x| x.unwrap().parse::<i64>().unwrap());
+
+    let (_, n) = invalid_numbers(data, window_size)
+        .next()
+        .ok_or("Solution not found")?;
+
+    Ok(n)
 }
 
-fn solve_part2<T: BufRead>(input: Lines<T>, window_size: usize) -> i64 {
+fn solve_part2<T: BufRead>(input: Lines<T>, window_size: usize) -> Result<i64, Box<dyn Error>> {
     let data = input
         .map(|x| x.unwrap().parse::<i64>().unwrap())
         .collect::<Vec<i64>>();
 
-    let part1 = data
-        .windows(window_size + 1)
-        .map(|xs| {
-            (
-                xs.last().unwrap(),
-                xs.iter().take(window_size).collect::<Vec<&i64>>(),
-            )
-        })
-        .filter(|(n, window)| {
-            window
-                .iter()
-                .enumerate()
-                .cartesian_product(window.iter().enumerate())
-                .take(window.len().pow(2))
-                .filter_map(
-                    |((i1, n1), (i2, n2))| {
-                        if i1 != i2 {
-                            Some(*n1 + *n2)
-                        } else {
-                            None
-                        }
-                    },
+    #[cfg(not(feature = "parallel"))]
+    let (first_invalid, find_range) = (
+        invalid_numbers(data.iter().cloned(), window_size).next(),
+        find_contiguous_sum,
+    );
+    #[cfg(feature = "parallel")]
+    let (first_invalid, find_range) = (
+        par_invalid_numbers(&data, window_size).find_first(|_| true),
+        par_find_contiguous_sum,
+    );
+
+    let (_, invalid_number) = first_invalid.ok_or("Solution not found")?;
+    let range = find_range(&data, invalid_number, 2).ok_or("Solution not found")?;
+    let xs = &data[range];
+    let min = *xs.iter().min().unwrap();
+    let max = *xs.iter().max().unwrap();
+
+    Ok(checked_sum(&[min, max])?)
+}
+
+/// The last `preamble` numbers of the stream, checking a number is O(preamble) and sliding the
+/// window is O(1).
+#[derive(Debug, Clone)]
+pub struct XmasWindow {
+    window: Window<i64>,
+}
+
+impl XmasWindow {
+    pub fn new(preamble: usize) -> Self {
+        Self {
+            window: Window::new(preamble),
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.window.is_full()
+    }
+
+    pub fn window(&self) -> &VecDeque<i64> {
+        self.window.items()
+    }
+
+    /// Two numbers at different positions of the window adding up to `n`, if any.
+    pub fn find_pair(&self, n: i64) -> Option<(i64, i64)> {
+        self.window.find_pair(n)
+    }
+
+    pub fn push(&mut self, n: i64) {
+        self.window.push(n);
+    }
+
+    /// Checks `n` against the window and slides it. Returns `None` while filling the preamble.
+    pub fn check(&mut self, n: i64) -> Option<bool> {
+        let valid = if self.is_full() {
+            Some(self.find_pair(n).is_some())
+        } else {
+            None
+        };
+        self.push(n);
+
+        valid
+    }
+}
+
+/// Every number of `data` (with its index) that isn't the sum of two of the `preamble` numbers
+/// before it.
+pub fn invalid_numbers<I>(data: I, preamble: usize) -> impl Iterator<Item = (usize, i64)>
+where
+    I: IntoIterator<Item = i64>,
+{
+    let mut window = XmasWindow::new(preamble);
+
+    data.into_iter()
+        .enumerate()
+        .filter(move |&(_, n)| window.check(n) == Some(false))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "verdict", rename_all = "lowercase")]
+pub enum XmasVerdict {
+    Valid { pair: (i64, i64) },
+    Invalid { window: Vec<i64> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct XmasEntry {
+    pub index: usize,
+    pub value: i64,
+    #[serde(flatten)]
+    pub verdict: XmasVerdict,
+}
+
+impl fmt::Display for XmasEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.verdict {
+            XmasVerdict::Valid { pair: (a, b) } => {
+                write!(f, "{}\t{}\tvalid: {} + {}", self.index, self.value, a, b)
+            }
+            XmasVerdict::Invalid { window } => {
+                let window: Vec<String> = window.iter().map(|x| x.to_string()).collect();
+                write!(
+                    f,
+                    "{}\t{}\tinvalid, window: {}",
+                    self.index,
+                    self.value,
+                    window.join(", ")
                 )
-                .all(|sum| sum != **n)
-        })
-        .map(|(n, _)| *n)
-        .take(1)
-        .map(|a| a)
-        .collect::<Vec<i64>>();
+            }
+        }
+    }
+}
 
-    let invalid_number = *part1.first().unwrap();
+/// Validates numbers as they are read, one per line, keeping only the preamble in memory.
+/// Yields every invalid number along with the window it was checked against, blank lines are
+/// skipped.
+pub fn validate_stream<R: BufRead>(input: R, preamble: usize) -> XmasStream<R> {
+    XmasStream {
+        lines: input.lines(),
+        window: XmasWindow::new(preamble),
+        explain: false,
+        line_number: 0,
+        index: 0,
+    }
+}
 
-    let result: Vec<i64> = (0..data.len())
-        .filter_map(|i| compute_sums_list(&data, i, invalid_number))
-        .map(|xs| xs.iter().min().unwrap() + xs.iter().max().unwrap())
-        .take(1)
-        .collect();
+pub struct XmasStream<R> {
+    lines: Lines<R>,
+    window: XmasWindow,
+    explain: bool,
+    line_number: usize,
+    index: usize,
+}
 
-    *result.first().unwrap()
+impl<R> XmasStream<R> {
+    /// Also yields valid numbers, with the pair adding up to them.
+    pub fn explain(mut self) -> Self {
+        self.explain = true;
+        self
+    }
 }
 
-fn compute_sums_list(input: &Vec<i64>, start: usize, target: i64) -> Option<Vec<i64>> {
-    let mut result = Vec::new();
-    let mut sum = 0;
+impl<R: BufRead> Iterator for XmasStream<R> {
+    type Item = Result<XmasEntry, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for line in &mut self.lines {
+            self.line_number += 1;
+
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
 
-    for n in &input[start..] {
-        result.push(*n);
-        sum += n;
+            let value = match line.trim().parse::<i64>() {
+                Ok(n) => n,
+                Err(e) => return Some(Err(format!("line {}: {}", self.line_number, e).into())),
+            };
 
-        if sum == target && result.len() > 1 {
-            return Some(result);
-        } else if sum > target {
-            return None;
+            let index = self.index;
+            self.index += 1;
+
+            if !self.window.is_full() {
+                self.window.push(value);
+                continue;
+            }
+
+            let verdict = match self.window.find_pair(value) {
+                Some(pair) if self.explain => Some(XmasVerdict::Valid { pair }),
+                Some(_) => None,
+                None => Some(XmasVerdict::Invalid {
+                    window: self.window.window().iter().cloned().collect(),
+                }),
+            };
+            self.window.push(value);
+
+            if let Some(verdict) = verdict {
+                return Some(Ok(XmasEntry {
+                    index,
+                    value,
+                    verdict,
+                }));
+            }
         }
+
+        None
     }
+}
+
+/// Bounds of the contiguous range of at least `min_len` numbers adding up to `target` with the
+/// smallest start (and then the smallest end), see [`seq::contiguous_sum`].
+pub fn find_contiguous_sum(data: &[i64], target: i64, min_len: usize) -> Option<Range<usize>> {
+    // Sums of fewer than `isize::MAX` numbers of `i64` always fit in `i128`.
+    seq::contiguous_sum::<i64, i128>(data, target.into(), min_len).unwrap()
+}
+
+/// Same as [`invalid_numbers`], checking every number against its window in parallel.
+#[cfg(feature = "parallel")]
+pub fn par_invalid_numbers(
+    data: &[i64],
+    preamble: usize,
+) -> impl ParallelIterator<Item = (usize, i64)> + '_ {
+    (preamble.min(data.len())..data.len())
+        .into_par_iter()
+        .map_init(HashSet::new, move |seen, i| {
+            (i, data[i], is_invalid(data, preamble, i, seen))
+        })
+        .filter_map(|(i, n, invalid)| if invalid { Some((i, n)) } else { None })
+}
+
+/// Whether no two numbers of the window before `i` add up to `data[i]`, `seen` is scratch space
+/// reused between calls.
+#[cfg(feature = "parallel")]
+fn is_invalid(data: &[i64], preamble: usize, i: usize, seen: &mut HashSet<i64>) -> bool {
+    let n = data[i];
+    seen.clear();
+
+    !data[i - preamble..i].iter().any(|&x| {
+        let found = n.checked_sub(x).is_some_and(|y| seen.contains(&y));
+        seen.insert(x);
+        found
+    })
+}
 
-    None
+/// Same as [`find_contiguous_sum`], searching the candidate starts in parallel.
+#[cfg(feature = "parallel")]
+pub fn par_find_contiguous_sum(data: &[i64], target: i64, min_len: usize) -> Option<Range<usize>> {
+    seq::par_contiguous_sum::<i64, i128>(data, target.into(), min_len).unwrap()
 }
 
 #[cfg(test)]
@@ -133,7 +294,7 @@ mod tests {
 576
 ";
         let line_reader = Cursor::new(content).lines();
-        let solution = solve_part1(line_reader, 5);
+        let solution = solve_part1(line_reader, 5).unwrap();
 
         assert_eq!(solution, 127)
     }
@@ -142,7 +303,7 @@ mod tests {
     fn part1_input() {
         let content = File::open("inputs/puzzle09.input").unwrap();
         let line_reader = BufReader::new(content).lines();
-        let solution = solve_part1(line_reader, 25);
+        let solution = solve_part1(line_reader, 25).unwrap();
 
         assert_eq!(solution, 1930745883)
     }
@@ -172,7 +333,7 @@ mod tests {
 576
 ";
         let line_reader = Cursor::new(content).lines();
-        let solution = solve_part2(line_reader, 5);
+        let solution = solve_part2(line_reader, 5).unwrap();
 
         assert_eq!(solution, 62)
     }
@@ -181,8 +342,115 @@ mod tests {
     fn part2_input() {
         let content = File::open("inputs/puzzle09.input").unwrap();
         let line_reader = BufReader::new(content).lines();
-        let solution = solve_part2(line_reader, 25);
+        let solution = solve_part2(line_reader, 25).unwrap();
 
         assert_eq!(solution, 268878261)
     }
+
+    #[test]
+    fn overflow() {
+        let content = format!("{}\n1\n{}\n-5\n", i64::MAX - 1, i64::MAX);
+        let line_reader = Cursor::new(content).lines();
+        let solution = solve_part1(line_reader, 2).unwrap();
+
+        assert_eq!(solution, -5);
+
+        let content = format!("{}\n{}\n5\n{}\n", i64::MAX, 2, i64::MAX);
+        let line_reader = Cursor::new(content).lines();
+
+        assert!(solve_part2(line_reader, 2).is_err());
+    }
+
+    #[test]
+    fn all_invalid_numbers() {
+        let data = vec![1, 1, 2, 3, 10, 13, 23, 2, 3, 4];
+        let invalid: Vec<(usize, i64)> = invalid_numbers(data, 2).collect();
+
+        assert_eq!(invalid, vec![(4, 10), (7, 2), (8, 3), (9, 4)]);
+
+        let content = File::open("inputs/puzzle09.input").unwrap();
+        let data = BufReader::new(content)
+            .lines()
+            .map(|x| x.unwrap().parse::<i64>().unwrap());
+
+        assert_eq!(invalid_numbers(data, 25).count(), 1);
+    }
+
+    #[test]
+    fn stream() {
+        let content = "1\n1\n\n2\n3\n10\n13\nfoo\n23\n";
+        let mut stream = validate_stream(Cursor::new(content), 2);
+
+        assert_eq!(
+            stream.next().unwrap().unwrap(),
+            XmasEntry {
+                index: 4,
+                value: 10,
+                verdict: XmasVerdict::Invalid { window: vec![2, 3] },
+            }
+        );
+        assert_eq!(
+            stream.next().unwrap().unwrap_err().to_string(),
+            "line 8: invalid digit found in string"
+        );
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn explain() {
+        let content = "1\n1\n2\n3\n10\n";
+        let entries: Vec<XmasEntry> = validate_stream(Cursor::new(content), 2)
+            .explain()
+            .map(|entry| entry.unwrap())
+            .collect();
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].verdict, XmasVerdict::Valid { pair: (1, 1) });
+        assert_eq!(entries[1].to_string(), "3\t3\tvalid: 1 + 2");
+        assert_eq!(entries[2].to_string(), "4\t10\tinvalid, window: 2, 3");
+        assert_eq!(
+            serde_json::to_string(&entries[2]).unwrap(),
+            r#"{"index":4,"value":10,"verdict":"invalid","window":[2,3]}"#
+        );
+    }
+
+    #[test]
+    fn contiguous_sums() {
+        let data = [
+            35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127,
+        ];
+
+        assert_eq!(find_contiguous_sum(&data, 127, 2), Some(2..6));
+        assert_eq!(find_contiguous_sum(&data, 127, 1), Some(2..6));
+        assert_eq!(find_contiguous_sum(&data, 1, 1), None);
+        assert_eq!(find_contiguous_sum(&[5, -3, 1, 2, 0], 0, 2), Some(1..4));
+        assert_eq!(find_contiguous_sum(&[4, 0, 0], 0, 2), Some(1..3));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_matches_sequential() {
+        let mut rng = crate::generators::Rng::new(39);
+
+        for offset in &[0, 50] {
+            let data: Vec<i64> = (0..20_000)
+                .map(|_| rng.range(0, 100) as i64 - offset)
+                .collect();
+
+            let expected: Vec<(usize, i64)> = invalid_numbers(data.iter().cloned(), 25).collect();
+            let found: Vec<(usize, i64)> = par_invalid_numbers(&data, 25).collect();
+            assert_eq!(found, expected);
+
+            for _ in 0..20 {
+                let start = rng.range(0, 19_000) as usize;
+                let end = start + rng.range(2, 1_000) as usize;
+                let target = data[start..end].iter().sum();
+
+                assert_eq!(
+                    par_find_contiguous_sum(&data, target, 2),
+                    find_contiguous_sum(&data, target, 2)
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,558 @@
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt,
+    sync::OnceLock,
+};
+
+const PASSPORT: &str = include_str!("../../schemas/passport.json");
+const STRICT_HCL: &str = "^#[0-9a-f]{6}$";
+
+/// Rules for the fields of `key:value` records, loaded from JSON such as:
+///
+/// ```json
+/// {
+///   "name": "passport",
+///   "fields": [
+///     { "name": "byr", "type": "integer", "min": 1920, "max": 2020 },
+///     { "name": "hgt", "type": "measure", "units": { "cm": { "min": 150, "max": 193 } } },
+///     { "name": "hcl", "type": "string", "pattern": "^#[0-9a-f]{6}$" },
+///     { "name": "ecl", "type": "enum", "values": ["amb", "blu"] },
+///     { "name": "cid", "type": "string", "required": false }
+///   ]
+/// }
+/// ```
+///
+/// Fields are required unless stated otherwise, fields missing from the schema are ignored.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Schema {
+    pub name: String,
+    pub fields: Vec<FieldSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldSpec {
+    pub name: String,
+    #[serde(default = "required")]
+    pub required: bool,
+    #[serde(flatten)]
+    pub kind: FieldKind,
+}
+
+fn required() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FieldKind {
+    /// A whole number within the bounds.
+    Integer {
+        #[serde(flatten)]
+        bounds: Bounds,
+    },
+    /// A whole number followed by a unit, with bounds depending on the unit.
+    Measure { units: BTreeMap<String, Bounds> },
+    /// Any text, optionally matching a regex compiled when the schema is loaded.
+    String {
+        #[serde(default, deserialize_with = "regex")]
+        pattern: Option<Regex>,
+    },
+    /// One of a fixed set of values.
+    Enum { values: Vec<String> },
+}
+
+fn regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Regex>, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub struct Bounds {
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+impl Bounds {
+    /// Why `value` is out of bounds, `shown` is how the value appears in the reason.
+    fn check(&self, value: i64, shown: &str) -> Result<(), String> {
+        match (self.min, self.max) {
+            (Some(min), _) if value < min => Err(format!("{} is below {}", shown, min)),
+            (_, Some(max)) if value > max => Err(format!("{} exceeds {}", shown, max)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "error", content = "reason", rename_all = "lowercase")]
+pub enum FieldError {
+    Missing,
+    Invalid(String),
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldError::Missing => write!(f, "missing"),
+            FieldError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// Every field of a record failing its schema, [`Validation::is_valid`] if there are none.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Validation {
+    pub missing: Vec<String>,
+    pub invalid: Vec<InvalidField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InvalidField {
+    pub field: String,
+    pub reason: String,
+}
+
+impl Validation {
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.invalid.is_empty()
+    }
+}
+
+/// `valid`, or each failure such as `missing iyr` or `hgt 190in exceeds 76` separated by `; `.
+impl fmt::Display for Validation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_valid() {
+            return write!(f, "valid");
+        }
+
+        let failures: Vec<String> = self
+            .missing
+            .iter()
+            .map(|field| format!("missing {}", field))
+            .chain(
+                self.invalid
+                    .iter()
+                    .map(|invalid| format!("{} {}", invalid.field, invalid.reason)),
+            )
+            .collect();
+
+        write!(f, "{}", failures.join("; "))
+    }
+}
+
+/// Failure counts per field over a batch of records.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Summary {
+    pub records: usize,
+    pub valid: usize,
+    pub fields: Vec<FieldSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldSummary {
+    pub field: String,
+    pub missing: usize,
+    pub invalid: usize,
+}
+
+impl Summary {
+    pub fn new(schema: &Schema) -> Self {
+        let fields = schema
+            .fields
+            .iter()
+            .map(|spec| FieldSummary {
+                field: spec.name.clone(),
+                missing: 0,
+                invalid: 0,
+            })
+            .collect();
+
+        Self {
+            records: 0,
+            valid: 0,
+            fields,
+        }
+    }
+
+    pub fn add(&mut self, validation: &Validation) {
+        self.records += 1;
+        if validation.is_valid() {
+            self.valid += 1;
+        }
+
+        for field in &mut self.fields {
+            if validation.missing.contains(&field.field) {
+                field.missing += 1;
+            }
+            if validation.invalid.iter().any(|i| i.field == field.field) {
+                field.invalid += 1;
+            }
+        }
+    }
+}
+
+/// A `field\tmissing\tinvalid` table followed by the number of valid records.
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "field\tmissing\tinvalid")?;
+        for field in &self.fields {
+            writeln!(f, "{}\t{}\t{}", field.field, field.missing, field.invalid)?;
+        }
+
+        write!(f, "valid: {} of {}", self.valid, self.records)
+    }
+}
+
+impl Schema {
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// The passport rules of puzzle 04, from `schemas/passport.json`. They are loaded once per
+    /// process and shared, so their patterns are only compiled once.
+    pub fn passport() -> &'static Self {
+        static SCHEMA: OnceLock<Schema> = OnceLock::new();
+        SCHEMA.get_or_init(|| Self::from_json(PASSPORT).unwrap())
+    }
+
+    /// The passport rules with `hcl` held to the puzzle spec, rejecting the `#rgb` shorthand and
+    /// uppercase digits the default rules let through.
+    pub fn passport_strict() -> &'static Self {
+        static SCHEMA: OnceLock<Schema> = OnceLock::new();
+        SCHEMA.get_or_init(|| {
+            let mut schema = Self::passport().clone();
+            let pattern = Regex::new(STRICT_HCL).unwrap();
+            for field in schema.fields.iter_mut().filter(|field| field.name == "hcl") {
+                field.kind = FieldKind::String {
+                    pattern: Some(pattern.clone()),
+                };
+            }
+
+            schema
+        })
+    }
+
+    /// Checks every field of the schema against `record`, in schema order.
+    pub fn check<'a>(
+        &'a self,
+        record: &HashMap<String, String>,
+    ) -> Vec<(&'a str, Result<(), FieldError>)> {
+        self.fields
+            .iter()
+            .map(|field| {
+                let result = match record.get(&field.name) {
+                    Some(value) => field.kind.check(value).map_err(FieldError::Invalid),
+                    None if field.required => Err(FieldError::Missing),
+                    None => Ok(()),
+                };
+
+                (field.name.as_str(), result)
+            })
+            .collect()
+    }
+
+    pub fn validate(&self, record: &HashMap<String, String>) -> Validation {
+        let mut validation = Validation::default();
+
+        for (name, result) in self.check(record) {
+            match result {
+                Ok(()) => (),
+                Err(FieldError::Missing) => validation.missing.push(name.to_string()),
+                Err(FieldError::Invalid(reason)) => validation.invalid.push(InvalidField {
+                    field: name.to_string(),
+                    reason,
+                }),
+            }
+        }
+
+        validation
+    }
+
+    pub fn is_valid(&self, record: &HashMap<String, String>) -> bool {
+        self.check(record).iter().all(|(_, result)| result.is_ok())
+    }
+}
+
+impl FieldKind {
+    /// Why `value` doesn't comply with the field rules.
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        match self {
+            FieldKind::Integer { bounds } => {
+                let n: i64 = value
+                    .parse()
+                    .map_err(|_| format!("'{}' is not an integer", value))?;
+                bounds.check(n, value)
+            }
+            FieldKind::Measure { units } => {
+                let expected = || units.keys().cloned().collect::<Vec<_>>().join(" or ");
+                let split = value
+                    .find(|c: char| !c.is_ascii_digit() && c != '-' && c != '+')
+                    .ok_or_else(|| format!("'{}' has no unit, expected {}", value, expected()))?;
+                let (number, unit) = value.split_at(split);
+
+                let bounds = units.get(unit).ok_or_else(|| {
+                    format!("unknown unit in '{}', expected {}", value, expected())
+                })?;
+                let n: i64 = number
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a number of {}", value, unit))?;
+                bounds.check(n, value)
+            }
+            FieldKind::String { pattern } => match pattern {
+                Some(re) if !re.is_match(value) => Err(format!("'{}' doesn't match {}", value, re)),
+                _ => Ok(()),
+            },
+            FieldKind::Enum { values } => {
+                if values.iter().any(|v| v == value) {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' is not one of {}", value, values.join(", ")))
+                }
+            }
+        }
+    }
+}
+
+/// Fields of a `key:value` record along with the problems found while reading it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Record {
+    pub fields: HashMap<String, String>,
+    pub errors: Vec<RecordError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "error", rename_all = "lowercase")]
+pub enum RecordError {
+    /// A token without a key, a `:` or a value.
+    Malformed { token: String },
+    /// A key seen before, its first value is kept.
+    Repeated { key: String, value: String },
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Malformed { token } => write!(f, "malformed token '{}'", token),
+            RecordError::Repeated { key, value } => {
+                write!(f, "repeated key {}, ignored '{}'", key, value)
+            }
+        }
+    }
+}
+
+impl Error for RecordError {}
+
+/// Parses a record of whitespace separated `key:value` tokens, any mix of spaces, tabs, CRLF or
+/// LF line breaks. Malformed tokens and repeated keys are recorded and skipped rather than
+/// failing the whole record.
+pub fn parse_record(raw: &str) -> Record {
+    let mut record = Record::default();
+
+    for token in raw.split_whitespace() {
+        let (key, value) = match token.split_once(':') {
+            Some((key, value)) if !key.is_empty() && !value.is_empty() => (key, value),
+            _ => {
+                record.errors.push(RecordError::Malformed {
+                    token: token.to_string(),
+                });
+                continue;
+            }
+        };
+
+        if record.fields.contains_key(key) {
+            record.errors.push(RecordError::Repeated {
+                key: key.to_string(),
+                value: value.to_string(),
+            });
+        } else {
+            record.fields.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passport_schema() {
+        let schema = Schema::passport();
+        let check = |raw: &str| -> Vec<(String, FieldError)> {
+            schema
+                .check(&parse_record(raw).fields)
+                .into_iter()
+                .filter_map(|(name, result)| Some((name.to_string(), result.err()?)))
+                .collect()
+        };
+
+        let valid = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f";
+        assert_eq!(check(valid), vec![]);
+        assert!(schema.is_valid(&parse_record(valid).fields));
+
+        let errors = check("hgt:190in ecl:zzz iyr:2009 eyr:x byr:2021 hcl:123abc pid:0123456789");
+        let invalid = |reason: &str| FieldError::Invalid(reason.to_string());
+        assert_eq!(
+            errors,
+            vec![
+                ("byr".to_string(), invalid("2021 exceeds 2020")),
+                ("iyr".to_string(), invalid("2009 is below 2010")),
+                ("eyr".to_string(), invalid("'x' is not an integer")),
+                ("hgt".to_string(), invalid("190in exceeds 76")),
+                (
+                    "hcl".to_string(),
+                    invalid("'123abc' doesn't match ^#(?:[0-9a-fA-F]{3}){1,2}$")
+                ),
+                (
+                    "ecl".to_string(),
+                    invalid("'zzz' is not one of amb, blu, brn, gry, grn, hzl, oth")
+                ),
+                (
+                    "pid".to_string(),
+                    invalid("'0123456789' doesn't match ^[0-9]{9}$")
+                ),
+            ]
+        );
+
+        assert_eq!(
+            check("hgt:190 byr:1980")[2],
+            (
+                "hgt".to_string(),
+                invalid("'190' has no unit, expected cm or in")
+            )
+        );
+        assert_eq!(
+            check("hgt:6ft byr:1980")[2],
+            (
+                "hgt".to_string(),
+                invalid("unknown unit in '6ft', expected cm or in")
+            )
+        );
+        assert_eq!(
+            check("byr:1980")[0],
+            ("iyr".to_string(), FieldError::Missing)
+        );
+    }
+
+    #[test]
+    fn strict_passport_schema() {
+        assert!(std::ptr::eq(Schema::passport(), Schema::passport()));
+
+        let strict = Schema::passport_strict();
+        let valid = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f";
+        assert!(strict.is_valid(&parse_record(valid).fields));
+
+        for hcl in &["#623", "#623A2F"] {
+            let record = parse_record(&valid.replace("#623a2f", hcl)).fields;
+            assert!(Schema::passport().is_valid(&record));
+            assert!(!strict.is_valid(&record));
+        }
+    }
+
+    #[test]
+    fn custom_schema() {
+        let schema = Schema::from_json(
+            r#"{
+                "name": "order",
+                "fields": [
+                    { "name": "qty", "type": "integer", "min": 1 },
+                    { "name": "weight", "type": "measure", "units": { "kg": { "max": 20 } } },
+                    { "name": "note", "type": "string", "required": false }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(schema.is_valid(&parse_record("qty:3 weight:12kg").fields));
+        assert!(schema.is_valid(&parse_record("qty:3 weight:-1kg note:fragile").fields));
+        assert!(!schema.is_valid(&parse_record("qty:0 weight:12kg").fields));
+        assert!(!schema.is_valid(&parse_record("qty:3 weight:21kg").fields));
+        assert!(!schema.is_valid(&parse_record("qty:3").fields));
+
+        assert!(Schema::from_json(r#"{ "name": "x", "fields": [{ "name": "a" }] }"#).is_err());
+        assert!(Schema::from_json(
+            r#"{ "name": "x", "fields": [{ "name": "a", "type": "string", "pattern": "(" }] }"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn diagnostics() {
+        let schema = Schema::passport();
+        let mut summary = Summary::new(schema);
+
+        let validation = schema.validate(&parse_record("hgt:190in byr:1980 ecl:zzz").fields);
+        assert_eq!(validation.missing, vec!["iyr", "eyr", "hcl", "pid"]);
+        assert_eq!(
+            validation.to_string(),
+            "missing iyr; missing eyr; missing hcl; missing pid; hgt 190in exceeds 76; \
+             ecl 'zzz' is not one of amb, blu, brn, gry, grn, hzl, oth"
+        );
+        summary.add(&validation);
+
+        let validation = schema.validate(
+            &parse_record("pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f")
+                .fields,
+        );
+        assert!(validation.is_valid());
+        assert_eq!(validation.to_string(), "valid");
+        summary.add(&validation);
+
+        summary.add(&schema.validate(&parse_record("hgt:58in").fields));
+
+        let counts: Vec<(&str, usize, usize)> = summary
+            .fields
+            .iter()
+            .map(|f| (f.field.as_str(), f.missing, f.invalid))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("byr", 1, 0),
+                ("iyr", 2, 0),
+                ("eyr", 2, 0),
+                ("hgt", 0, 2),
+                ("hcl", 2, 0),
+                ("ecl", 1, 1),
+                ("pid", 2, 0),
+                ("cid", 0, 0),
+            ]
+        );
+        assert_eq!((summary.records, summary.valid), (3, 1));
+        assert!(summary.to_string().ends_with("cid\t0\t0\nvalid: 1 of 3"));
+    }
+
+    #[test]
+    fn tolerant_records() {
+        let record =
+            parse_record("byr:1980 \r\nhcl:#a:b\tbroken :x byr:1990 cid:\r\n  iyr:2012  \r\n");
+
+        let mut fields: Vec<(&str, &str)> = record
+            .fields
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        fields.sort_unstable();
+        assert_eq!(
+            fields,
+            vec![("byr", "1980"), ("hcl", "#a:b"), ("iyr", "2012")]
+        );
+
+        let errors: Vec<String> = record.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "malformed token 'broken'",
+                "malformed token ':x'",
+                "repeated key byr, ignored '1990'",
+                "malformed token 'cid:'",
+            ]
+        );
+    }
+}
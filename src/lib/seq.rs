@@ -0,0 +1,434 @@
+use itertools::Itertools;
+use num_traits::{CheckedAdd, CheckedMul, CheckedSub, Num, One, Zero};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    ops::Range,
+};
+
+use crate::OverflowError;
+
+/// Multiplies `xs` in `R`, use a type wider than `T` (or `BigInt`) to make room for the result.
+pub fn compute_product<T, R>(xs: &[T]) -> Result<R, OverflowError>
+where
+    T: Copy + Into<R>,
+    R: CheckedMul + One,
+{
+    xs.iter()
+        .try_fold(R::one(), |acc, x| acc.checked_mul(&(*x).into()))
+        .ok_or(OverflowError)
+}
+
+/// Adds `xs` in `R`, use a type wider than `T` (or `BigInt`) to make room for the result.
+pub fn checked_sum<T, R>(xs: &[T]) -> Result<R, OverflowError>
+where
+    T: Copy + Into<R>,
+    R: CheckedAdd + Zero,
+{
+    xs.iter()
+        .try_fold(R::zero(), |acc, x| acc.checked_add(&(*x).into()))
+        .ok_or(OverflowError)
+}
+
+pub fn check_sum_equals<T, R>(xs: &[T], target: R) -> Result<bool, OverflowError>
+where
+    T: Copy + Into<R>,
+    R: CheckedAdd + Zero + PartialEq,
+{
+    Ok(checked_sum(xs)? == target)
+}
+
+/// Every combination of `k` entries at distinct positions, in lexicographic order of positions.
+pub fn k_combinations<T: Copy + Num>(xs: &[T], k: usize) -> impl Iterator<Item = Vec<T>> + '_ {
+    xs.iter().copied().combinations(k)
+}
+
+/// Positions `i < j` of two entries adding up to `target`, for the smallest such `j` and the
+/// first occurrence of its complement.
+pub fn pair_sum<T>(xs: &[T], target: T) -> Option<(usize, usize)>
+where
+    T: Copy + Num + CheckedSub + Hash + Eq,
+{
+    let mut seen: HashMap<T, usize> = HashMap::new();
+
+    for (j, &x) in xs.iter().enumerate() {
+        // No complement fits in `T` when the subtraction overflows.
+        if let Some(&i) = target.checked_sub(&x).and_then(|y| seen.get(&y)) {
+            return Some((i, j));
+        }
+        seen.entry(x).or_insert(j);
+    }
+
+    None
+}
+
+/// The last `capacity` entries of a sequence along with how many times each value appears, so
+/// looking up a pair is O(capacity) and sliding the window is O(1).
+#[derive(Debug, Clone)]
+pub struct Window<T> {
+    capacity: usize,
+    items: VecDeque<T>,
+    counts: HashMap<T, usize>,
+}
+
+impl<T> Window<T>
+where
+    T: Copy + Num + CheckedSub + Hash + Eq,
+{
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            items: VecDeque::with_capacity(capacity + 1),
+            counts: HashMap::with_capacity(capacity + 1),
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.items.len() == self.capacity
+    }
+
+    pub fn items(&self) -> &VecDeque<T> {
+        &self.items
+    }
+
+    pub fn contains(&self, x: &T) -> bool {
+        self.counts.contains_key(x)
+    }
+
+    /// Two entries at different positions of the window adding up to `target`, if any.
+    pub fn find_pair(&self, target: T) -> Option<(T, T)> {
+        self.items.iter().find_map(|&x| {
+            let y = target.checked_sub(&x)?;
+            let needed = if x == y { 2 } else { 1 };
+
+            match self.counts.get(&y) {
+                Some(&count) if count >= needed => Some((x, y)),
+                _ => None,
+            }
+        })
+    }
+
+    /// Appends `x`, returning the entry that slid out of the window.
+    pub fn push(&mut self, x: T) -> Option<T> {
+        self.items.push_back(x);
+        *self.counts.entry(x).or_insert(0) += 1;
+
+        if self.items.len() <= self.capacity {
+            return None;
+        }
+
+        let old = self.items.pop_front().unwrap();
+        let count = self.counts.get_mut(&old).unwrap();
+
+        *count -= 1;
+        if *count == 0 {
+            self.counts.remove(&old);
+        }
+
+        Some(old)
+    }
+}
+
+/// Sum in `R` of every run of `len` consecutive entries, updated as the run slides.
+pub fn rolling_sums<'a, T, R>(
+    xs: &'a [T],
+    len: usize,
+) -> impl Iterator<Item = Result<R, OverflowError>> + 'a
+where
+    T: Copy + Into<R>,
+    R: Copy + CheckedAdd + CheckedSub + Zero + 'a,
+{
+    assert!(len > 0, "Windows need at least one entry");
+    let mut sum: Result<R, OverflowError> = Ok(R::zero());
+
+    xs.iter().enumerate().filter_map(move |(i, &x)| {
+        sum = sum.and_then(|s| s.checked_add(&x.into()).ok_or(OverflowError));
+        if i >= len {
+            sum = sum.and_then(|s| s.checked_sub(&xs[i - len].into()).ok_or(OverflowError));
+        }
+
+        if i + 1 >= len {
+            Some(sum)
+        } else {
+            None
+        }
+    })
+}
+
+/// Sums in `R` of the first `0..=xs.len()` entries.
+pub fn prefix_sums<T, R>(xs: &[T]) -> Result<Vec<R>, OverflowError>
+where
+    T: Copy + Into<R>,
+    R: Copy + CheckedAdd + Zero,
+{
+    let mut prefix = Vec::with_capacity(xs.len() + 1);
+    prefix.push(R::zero());
+
+    for &x in xs {
+        let last = *prefix.last().unwrap();
+        prefix.push(last.checked_add(&x.into()).ok_or(OverflowError)?);
+    }
+
+    Ok(prefix)
+}
+
+/// Bounds of the contiguous range of at least `min_len` entries adding up to `target` in `R`
+/// with the smallest start (and then the smallest end). Runs in linear time using two pointers
+/// when all entries are non-negative, falls back to a hash of prefix sums otherwise.
+pub fn contiguous_sum<T, R>(
+    xs: &[T],
+    target: R,
+    min_len: usize,
+) -> Result<Option<Range<usize>>, OverflowError>
+where
+    T: Copy + Num + PartialOrd + Into<R>,
+    R: Copy + Num + CheckedAdd + CheckedSub + Ord + Hash,
+{
+    if xs.iter().all(|&x| x >= T::zero()) {
+        contiguous_sum_two_pointers(xs, 0..xs.len(), target, min_len)
+    } else {
+        contiguous_sum_prefix(xs, target, min_len)
+    }
+}
+
+/// Two pointers over non-negative `xs`, only trying ranges beginning in `starts`.
+fn contiguous_sum_two_pointers<T, R>(
+    xs: &[T],
+    starts: Range<usize>,
+    target: R,
+    min_len: usize,
+) -> Result<Option<Range<usize>>, OverflowError>
+where
+    T: Copy + Into<R>,
+    R: Copy + Num + CheckedAdd + CheckedSub + Ord,
+{
+    let (mut end, mut sum) = (starts.start, R::zero());
+
+    for start in starts {
+        if end < start {
+            end = start;
+            sum = R::zero();
+        }
+
+        while end < xs.len() && (end - start < min_len || sum < target) {
+            sum = sum.checked_add(&xs[end].into()).ok_or(OverflowError)?;
+            end += 1;
+        }
+
+        if end - start >= min_len && sum == target {
+            return Ok(Some(start..end));
+        }
+
+        if end > start {
+            sum = sum.checked_sub(&xs[start].into()).ok_or(OverflowError)?;
+        }
+    }
+
+    Ok(None)
+}
+
+fn contiguous_sum_prefix<T, R>(
+    xs: &[T],
+    target: R,
+    min_len: usize,
+) -> Result<Option<Range<usize>>, OverflowError>
+where
+    T: Copy + Into<R>,
+    R: Copy + Num + CheckedAdd + CheckedSub + Hash + Eq,
+{
+    let prefix = prefix_sums(xs)?;
+
+    let mut first_seen: HashMap<R, usize> = HashMap::new();
+    let mut best: Option<Range<usize>> = None;
+
+    for end in min_len..prefix.len() {
+        first_seen
+            .entry(prefix[end - min_len])
+            .or_insert(end - min_len);
+
+        // No prefix fits in `R` when the subtraction overflows.
+        let start = prefix[end]
+            .checked_sub(&target)
+            .and_then(|sum| first_seen.get(&sum));
+
+        if let Some(&start) = start {
+            if best.as_ref().is_none_or(|b| start < b.start) {
+                best = Some(start..end);
+            }
+        }
+    }
+
+    Ok(best)
+}
+
+/// Starts per rayon task in [`par_contiguous_sum`].
+#[cfg(feature = "parallel")]
+const CHUNK: usize = 4096;
+
+/// Same as [`contiguous_sum`], searching the candidate starts in parallel.
+#[cfg(feature = "parallel")]
+pub fn par_contiguous_sum<T, R>(
+    xs: &[T],
+    target: R,
+    min_len: usize,
+) -> Result<Option<Range<usize>>, OverflowError>
+where
+    T: Copy + Num + PartialOrd + Into<R> + Sync,
+    R: Copy + Num + CheckedAdd + CheckedSub + Ord + Hash + Send + Sync,
+{
+    if !xs.par_iter().all(|&x| x >= T::zero()) {
+        return par_contiguous_sum_prefix(xs, target, min_len);
+    }
+
+    // Chunks see the same sums as the sequential scan, which stops at the first range or the
+    // first overflow before it.
+    (0..xs.len().div_ceil(CHUNK))
+        .into_par_iter()
+        .find_map_first(|chunk| {
+            let starts = chunk * CHUNK..xs.len().min((chunk + 1) * CHUNK);
+            match contiguous_sum_two_pointers(xs, starts, target, min_len) {
+                Ok(None) => None,
+                found => Some(found),
+            }
+        })
+        .unwrap_or(Ok(None))
+}
+
+/// Looks up, for every start, the first end whose prefix sum completes the target.
+#[cfg(feature = "parallel")]
+fn par_contiguous_sum_prefix<T, R>(
+    xs: &[T],
+    target: R,
+    min_len: usize,
+) -> Result<Option<Range<usize>>, OverflowError>
+where
+    T: Copy + Into<R>,
+    R: Copy + Num + CheckedAdd + Hash + Eq + Send + Sync,
+{
+    let prefix = prefix_sums(xs)?;
+
+    let mut positions: HashMap<R, Vec<usize>> = HashMap::new();
+    for (end, &sum) in prefix.iter().enumerate() {
+        positions.entry(sum).or_default().push(end);
+    }
+
+    Ok((0..prefix.len()).into_par_iter().find_map_first(|start| {
+        let ends = positions.get(&prefix[start].checked_add(&target)?)?;
+        let end = ends[ends.partition_point(|&end| end < start + min_len)..].first()?;
+
+        Some(start..*end)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::Rng;
+    use num_bigint::BigInt;
+
+    #[test]
+    fn checked_arithmetic() {
+        let xs = [i32::MAX, i32::MAX, 3];
+
+        assert_eq!(compute_product::<i32, i32>(&xs), Err(OverflowError));
+        assert_eq!(compute_product::<i32, i64>(&xs), Err(OverflowError));
+        assert_eq!(
+            compute_product::<i32, BigInt>(&xs),
+            Ok(BigInt::from(i32::MAX) * i32::MAX * 3)
+        );
+
+        assert_eq!(checked_sum::<i32, i32>(&xs), Err(OverflowError));
+        assert_eq!(checked_sum::<i32, i64>(&xs), Ok(2 * i32::MAX as i64 + 3));
+        assert_eq!(check_sum_equals(&[1010, 1010], 2020), Ok(true));
+    }
+
+    #[test]
+    fn pairs() {
+        assert_eq!(
+            pair_sum(&[1721, 979, 366, 299, 675, 1456], 2020),
+            Some((0, 3))
+        );
+        assert_eq!(pair_sum(&[1010, 5], 2020), None);
+        assert_eq!(pair_sum(&[1010, 5, 1010, 1010], 2020), Some((0, 2)));
+        assert_eq!(pair_sum(&[i8::MIN, 100, 27], 127), Some((1, 2)));
+
+        let mut window = Window::new(3);
+        assert_eq!(window.push(1u8), None);
+        assert_eq!(window.push(2), None);
+        assert_eq!(window.push(2), None);
+        assert!(window.is_full());
+        assert_eq!(window.find_pair(4), Some((2, 2)));
+        assert_eq!(window.find_pair(255), None);
+
+        assert_eq!(window.push(3), Some(1));
+        assert!(!window.contains(&1));
+        assert_eq!(window.find_pair(3), None);
+        assert_eq!(window.find_pair(5), Some((2, 3)));
+    }
+
+    #[test]
+    fn sums() {
+        let xs = [3i8, 100, 27, -50, 1];
+
+        assert_eq!(
+            prefix_sums::<i8, i16>(&xs),
+            Ok(vec![0, 3, 103, 130, 80, 81])
+        );
+        assert_eq!(prefix_sums::<i8, i8>(&xs), Err(OverflowError));
+
+        let rolling: Vec<_> = rolling_sums::<i8, i16>(&xs, 2).collect();
+        assert_eq!(rolling, vec![Ok(103), Ok(127), Ok(-23), Ok(-49)]);
+
+        let rolling: Vec<_> = rolling_sums::<i8, i8>(&xs, 3).collect();
+        assert_eq!(rolling, vec![Err(OverflowError); 3]);
+
+        let combinations: Vec<Vec<i8>> = k_combinations(&xs[..3], 2).collect();
+        assert_eq!(combinations, vec![vec![3, 100], vec![3, 27], vec![100, 27]]);
+    }
+
+    #[test]
+    fn contiguous_sums_match_brute_force() {
+        let mut rng = Rng::new(9);
+
+        for _ in 0..200 {
+            let len = rng.range(0, 30) as usize;
+            let offset = rng.range(0, 1) as i64 * 5;
+            let data: Vec<i64> = (0..len).map(|_| rng.range(0, 10) as i64 - offset).collect();
+            let target = rng.range(0, 30) as i64 - 10;
+            let min_len = rng.range(0, 3) as usize;
+
+            let expected = (0..=len)
+                .flat_map(|start| (start + min_len..=len).map(move |end| start..end))
+                .find(|r| data[r.clone()].iter().sum::<i64>() == target);
+
+            assert_eq!(
+                contiguous_sum_prefix(&data, target, min_len),
+                Ok(expected.clone())
+            );
+            if offset == 0 {
+                assert_eq!(
+                    contiguous_sum_two_pointers(&data, 0..len, target, min_len),
+                    Ok(expected)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn contiguous_sums_overflow() {
+        let xs = [100i8, 100, 27];
+
+        assert_eq!(contiguous_sum::<i8, i8>(&xs, 127, 1), Err(OverflowError));
+        assert_eq!(contiguous_sum::<i8, i16>(&xs, 127, 1), Ok(Some(1..3)));
+        assert_eq!(
+            contiguous_sum::<i8, i8>(&[-100, -100, 27], 127, 1),
+            Err(OverflowError)
+        );
+        assert_eq!(
+            contiguous_sum::<i8, i16>(&[-100, -100, 27], -73, 1),
+            Ok(Some(1..3))
+        );
+    }
+}